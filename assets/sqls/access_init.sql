@@ -9,9 +9,14 @@ CREATE TABLE IF NOT EXISTS access_request_logs (
     body_length             uint8 NOT NULL,
     created_at              TIMESTAMPTZ NOT NULL DEFAULT NOW(),
     requested_at            TIMESTAMPTZ NOT NULL DEFAULT NOW(),
-    website_id              TEXT
+    website_id              TEXT,
+    country                 TEXT,
+    region                  TEXT
 );
 
+ALTER TABLE access_request_logs ADD COLUMN IF NOT EXISTS country TEXT;
+ALTER TABLE access_request_logs ADD COLUMN IF NOT EXISTS region TEXT;
+
 CREATE TABLE IF NOT EXISTS access_response_logs (
     id                      TEXT PRIMARY KEY NOT NULL REFERENCES access_request_logs(id),
     status                  UINT2 NOT NULL,
@@ -21,9 +26,16 @@ CREATE TABLE IF NOT EXISTS access_response_logs (
     created_at              TIMESTAMPTZ NOT NULL DEFAULT NOW(),
     responsed_at            TIMESTAMPTZ NOT NULL DEFAULT NOW(),
     backend_responsed_at    TIMESTAMPTZ DEFAULT NOW(),
-    website_id              TEXT
+    website_id              TEXT,
+    cache_status            TEXT,
+    auth_status             TEXT,
+    auth_user               TEXT
 );
 
+ALTER TABLE access_response_logs ADD COLUMN IF NOT EXISTS cache_status TEXT;
+ALTER TABLE access_response_logs ADD COLUMN IF NOT EXISTS auth_status TEXT;
+ALTER TABLE access_response_logs ADD COLUMN IF NOT EXISTS auth_user TEXT;
+
 CREATE TABLE IF NOT EXISTS access_request_size_logs (
     id                      TEXT PRIMARY KEY NOT NULL,
     request_id              TEXT NOT NULL REFERENCES access_request_logs(id),
@@ -38,6 +50,16 @@ CREATE TABLE IF NOT EXISTS access_response_size_logs (
     created_at              TIMESTAMPTZ NOT NULL DEFAULT NOW()
 );
 
+CREATE TABLE IF NOT EXISTS access_attempt_logs (
+    request_id              TEXT NOT NULL REFERENCES access_request_logs(id),
+    attempt                 UINT2 NOT NULL,
+    backend                 TEXT NOT NULL,
+    status                  UINT2,
+    error                   TEXT,
+    elapsed                 uint8 NOT NULL,
+    created_at              TIMESTAMPTZ NOT NULL DEFAULT NOW(),
+    PRIMARY KEY (request_id, attempt)
+);
 
 CREATE INDEX IF NOT EXISTS idx_requested_at ON access_request_logs (requested_at);
 CREATE INDEX IF NOT EXISTS idx_responsed_at ON access_response_logs (responsed_at);
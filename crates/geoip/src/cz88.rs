@@ -1,9 +1,9 @@
 use std::{collections::HashMap, net::IpAddr, path::PathBuf, sync::LazyLock};
 
-use anyhow::Result;
+use anyhow::{Result, anyhow};
 use ipdb::{Reader};
 
-use crate::ip::{LookupResult, ROOT};
+use crate::{LookupResult, ROOT};
 
 static FILE: LazyLock<PathBuf> = LazyLock::new(|| ROOT.clone().join("qqwry.ipdb"));
 
@@ -13,7 +13,7 @@ pub struct Instance {
 
 static COUNTRY_CODE_MAPPINGS: LazyLock<HashMap<String, String>> = LazyLock::new(|| {
     let mut m = HashMap::new();
-    let content = include_str!("../../../../assets/ipdb/country_code_mappings");
+    let content = include_str!("../../../assets/ipdb/country_code_mappings");
     for line in content.lines() {
         let mut parts = line.split("|");
         let country = parts.next().unwrap();
@@ -25,7 +25,7 @@ static COUNTRY_CODE_MAPPINGS: LazyLock<HashMap<String, String>> = LazyLock::new(
 
 static CITY_CODE_MAPPINGS: LazyLock<HashMap<String, String>> = LazyLock::new(|| {
     let mut m = HashMap::new();
-    let content = include_str!("../../../../assets/ipdb/city_code_mappings");
+    let content = include_str!("../../../assets/ipdb/city_code_mappings");
     for line in content.lines() {
         let mut parts = line.split("|");
         let city = parts.next().unwrap();
@@ -63,12 +63,22 @@ impl Instance {
     }
 }
 
-static INSTANCE: LazyLock<Instance> = LazyLock::new(|| {
-    Instance::new(ipdb::Reader::open_file(&*FILE).unwrap())
+// 文件不存在时为 None，查询返回错误
+static INSTANCE: LazyLock<Option<Instance>> = LazyLock::new(|| {
+    ipdb::Reader::open_file(&*FILE).ok().map(Instance::new)
 });
 
+pub fn init() {
+    LazyLock::force(&INSTANCE);
+    LazyLock::force(&COUNTRY_CODE_MAPPINGS);
+    LazyLock::force(&CITY_CODE_MAPPINGS);
+}
+
 pub fn lookup(ip: IpAddr) -> Result<LookupResult> {
-    INSTANCE.lookup(ip).map(|mut v| {
+    let instance = INSTANCE
+        .as_ref()
+        .ok_or_else(|| anyhow!("{} is not available", FILE.display()))?;
+    instance.lookup(ip).map(|mut v| {
         v.country = v.country.as_ref().map(|country| {
             COUNTRY_CODE_MAPPINGS.get(country).unwrap_or(country).to_string()
         });
@@ -3,7 +3,7 @@ use std::{collections::HashMap, net::IpAddr, path::PathBuf, sync::LazyLock};
 use anyhow::anyhow;
 use ip2region::Searcher;
 
-use crate::ip::{LookupResult, ROOT};
+use crate::{LookupResult, ROOT};
 
 static V4_FILE: LazyLock<PathBuf> = LazyLock::new(|| ROOT.clone().join("ip2region_v4.xdb"));
 static V6_FILE: LazyLock<PathBuf> = LazyLock::new(|| ROOT.clone().join("ip2region_v6.xdb"));
@@ -15,7 +15,13 @@ pub struct LookupResult {
     pub city: Option<String>,
 }
 
+/// 预先读取数据库文件，避免首次查询时阻塞
+pub fn init() {
+    mmdb::init();
+    cz88::init();
+}
 
+/// 查询 IP 所在的国家和地区，数据库文件不存在时返回错误
 pub fn lookup(ip: IpAddr) -> anyhow::Result<LookupResult> {
     // let ip2region_result = ip2region::lookup(ip)?;
     // if let Some(country) = &ip2region_result.country && country == "CN" && ip2region_result.city.is_some() {
@@ -24,16 +30,9 @@ pub fn lookup(ip: IpAddr) -> anyhow::Result<LookupResult> {
     match mmdb::lookup(ip) {
         Ok(r) => {
             match &r.country {
-                Some(country) => {
-                    if country == "CN" {
-                        cz88::lookup(ip)
-                    } else {
-                        Ok(r)
-                    }
-                },
-                None => {
-                    cz88::lookup(ip)
-                }
+                // 纯真库查询失败时仍使用 mmdb 的结果
+                Some(country) if country != "CN" => Ok(r),
+                _ => cz88::lookup(ip).or(Ok(r)),
             }
         },
         Err(e) => {
@@ -1,7 +1,7 @@
 use geoip2::{City, Country, Reader};
 use std::{net::IpAddr, path::PathBuf, sync::LazyLock};
 
-use crate::ip::{LookupResult, ROOT};
+use crate::{LookupResult, ROOT};
 
 // Reader now holds a 'static reference to the mmdb data
 pub struct CityInstance {
@@ -12,11 +12,11 @@ pub struct CityInstance {
 }
 
 impl CityInstance {
-    pub fn new(data: &'static [u8]) -> Self {
-        Self {
+    pub fn new(data: &'static [u8]) -> anyhow::Result<Self> {
+        Ok(Self {
             _data: data,
-            reader: Reader::<City>::from_bytes(data).unwrap(),
-        }
+            reader: Reader::<City>::from_bytes(data).map_err(|e| anyhow::anyhow!(format!("{e:?}")))?,
+        })
     }
 
     pub fn lookup(&self, ip: IpAddr) -> anyhow::Result<LookupResult> {
@@ -42,11 +42,11 @@ pub struct CountryInstance {
 }
 
 impl CountryInstance {
-    pub fn new(data: &'static [u8]) -> Self {
-        Self {
+    pub fn new(data: &'static [u8]) -> anyhow::Result<Self> {
+        Ok(Self {
             _data: data,
-            reader: Reader::<Country>::from_bytes(data).unwrap(),
-        }
+            reader: Reader::<Country>::from_bytes(data).map_err(|e| anyhow::anyhow!(format!("{e:?}")))?,
+        })
     }
 
     pub fn lookup(&self, ip: IpAddr) -> anyhow::Result<LookupResult> {
@@ -65,23 +65,34 @@ impl CountryInstance {
 static CITY_FILE: LazyLock<PathBuf> = LazyLock::new(|| ROOT.clone().join("GeoLite2-City.mmdb"));
 static CHINA_COUNTRY_FILE: LazyLock<PathBuf> = LazyLock::new(|| ROOT.clone().join("China_Country.mmdb"));
 
-static CITY_INSTANCE: LazyLock<CityInstance> = LazyLock::new(|| {
-    let content = std::fs::read(CITY_FILE.clone()).unwrap();
+// 文件不存在或无法解析时为 None
+static CITY_INSTANCE: LazyLock<Option<CityInstance>> = LazyLock::new(|| {
+    let content = std::fs::read(CITY_FILE.clone()).ok()?;
     // Leak the Vec to obtain a 'static slice
     let leaked: &'static [u8] = Box::leak(Box::new(content));
-    CityInstance::new(leaked)
+    CityInstance::new(leaked).ok()
 });
 
-static CHINA_COUNTRY_INSTANCE: LazyLock<CountryInstance> = LazyLock::new(|| {
-    let content = std::fs::read(CHINA_COUNTRY_FILE.clone()).unwrap();
+static CHINA_COUNTRY_INSTANCE: LazyLock<Option<CountryInstance>> = LazyLock::new(|| {
+    let content = std::fs::read(CHINA_COUNTRY_FILE.clone()).ok()?;
     // Leak the Vec to obtain a 'static slice
     let leaked: &'static [u8] = Box::leak(Box::new(content));
-    CountryInstance::new(leaked)
+    CountryInstance::new(leaked).ok()
 });
 
+pub fn init() {
+    LazyLock::force(&CHINA_COUNTRY_INSTANCE);
+    LazyLock::force(&CITY_INSTANCE);
+}
+
 pub fn lookup(ip: IpAddr) -> anyhow::Result<LookupResult> {
-    match CHINA_COUNTRY_INSTANCE.lookup(ip) {
-        Ok(v) => Ok(v),
-        Err(_) => CITY_INSTANCE.lookup(ip),
+    if let Some(instance) = &*CHINA_COUNTRY_INSTANCE
+        && let Ok(v) = instance.lookup(ip)
+    {
+        return Ok(v);
+    }
+    match &*CITY_INSTANCE {
+        Some(instance) => instance.lookup(ip),
+        None => Err(anyhow::anyhow!("{} is not available", CITY_FILE.display())),
     }
 }
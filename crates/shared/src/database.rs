@@ -10,13 +10,17 @@ use tracing::event;
 
 use crate::database::{
     access::DatabaseAccessLogsInitializer, certificate::DatabaseCertificateInitializer,
-    dnsprovider::DatabaseDNSProviderInitializer, websites::DatabaseWebsiteInitializer,
+    configuration::DatabaseConfigurationInitlializer, dnsprovider::DatabaseDNSProviderInitializer,
+    ipaccess::DatabaseIpAccessListInitializer, ratelimit::DatabaseRateLimitInitializer,
+    websites::DatabaseWebsiteInitializer,
 };
 
 pub mod access;
 pub mod certificate;
 pub mod configuration;
 pub mod dnsprovider;
+pub mod ipaccess;
+pub mod ratelimit;
 pub mod websites;
 
 static PG_EXTENSION: &[&str; 2] = &["uint128", "btree_gin"];
@@ -253,5 +257,8 @@ async fn inner_init_database() -> anyhow::Result<()> {
     get_database().initialize_certificates().await?;
     get_database().initialize_websites().await?;
     get_database().initialize_access_logs().await?;
+    get_database().initialize_rate_limits().await?;
+    get_database().initialize_ip_access_lists().await?;
+    get_database().initialize_configuration().await?;
     Ok(())
 }
@@ -3,7 +3,7 @@ use std::collections::HashMap;
 use crate::{
     database::Database,
     models::access::{
-        AccessCreateRequest, AccessCreateResponse, AccessInfo, AccessInsertRequestSize,
+        AccessCreateAttempt, AccessCreateRequest, AccessCreateResponse, AccessInfo, AccessInsertRequestSize,
         AccessInsertResponseSize, AccessUpdateRequestSize, AccessUpdateResponseSize, DatabaseQPS,
         ResponseQPS, TodayMetricsInfoOfWebsite,
     },
@@ -36,7 +36,14 @@ pub trait DatabaseAccessLogsRepository {
     async fn get_qps_per_5s(&self, count: usize) -> anyhow::Result<ResponseQPS>;
     async fn get_access_info(&self, in_days: usize) -> anyhow::Result<AccessInfo>;
     async fn get_today_metrics_info_of_websites(&self) -> anyhow::Result<Vec<TodayMetricsInfoOfWebsite>>;
-    async fn get_requests_of_ips(&self, in_days: usize) -> anyhow::Result<HashMap<String, usize>>;
+    /// 按国家统计请求数，没有解析出国家的记为 Unknown
+    async fn get_requests_of_countries(&self, in_days: usize) -> anyhow::Result<HashMap<String, usize>>;
+    /// 按国家内的地区统计请求数
+    async fn get_requests_of_regions(
+        &self,
+        in_days: usize,
+        country: &str,
+    ) -> anyhow::Result<HashMap<String, usize>>;
 }
 
 #[async_trait]
@@ -116,17 +123,38 @@ impl DatabaseAccessLogsRepository for Database {
         })
     }
 
-    async fn get_requests_of_ips(&self, in_days: usize) -> anyhow::Result<HashMap<String, usize>> {
+    async fn get_requests_of_countries(&self, in_days: usize) -> anyhow::Result<HashMap<String, usize>> {
         let rows = sqlx::query_as::<_, (String, i64)>(
-            "SELECT remote_addr, COUNT(id) FROM access_request_logs 
+            "SELECT COALESCE(country, 'Unknown'), COUNT(id) FROM access_request_logs 
              WHERE requested_at > NOW() - INTERVAL '1 day' * $1 
-             GROUP BY remote_addr"
+             GROUP BY 1"
         )
         .bind(in_days as i64)
         .fetch_all(&self.pool)
         .await?;
     
-        Ok(rows.into_iter().map(|(addr, count)| (addr, count as usize)).collect())
+        Ok(rows.into_iter().map(|(country, count)| (country, count as usize)).collect())
+    }
+
+    async fn get_requests_of_regions(
+        &self,
+        in_days: usize,
+        country: &str,
+    ) -> anyhow::Result<HashMap<String, usize>> {
+        let rows = sqlx::query_as::<_, (String, i64)>(
+            "SELECT COALESCE(region, 'Unknown'), COUNT(id) FROM access_request_logs 
+             WHERE requested_at > NOW() - INTERVAL '1 day' * $1 AND country = $2 
+             GROUP BY 1",
+        )
+        .bind(in_days as i64)
+        .bind(country)
+        .fetch_all(&self.pool)
+        .await?;
+
+        Ok(rows
+            .into_iter()
+            .map(|(region, count)| (region, count as usize))
+            .collect())
     }
 
     async fn get_today_metrics_info_of_websites(&self) -> anyhow::Result<Vec<TodayMetricsInfoOfWebsite>> {
@@ -195,6 +223,10 @@ pub trait DatabaseAccessLogsModifyRepository {
         &self,
         requests: Vec<AccessInsertRequestSize>,
     ) -> anyhow::Result<()>;
+    async fn insert_batch_access_attempts(
+        &self,
+        attempts: Vec<AccessCreateAttempt>,
+    ) -> anyhow::Result<()>;
 }
 
 #[async_trait]
@@ -207,7 +239,7 @@ impl DatabaseAccessLogsModifyRepository for Database {
             return Ok(());
         }
         let mut builder = QueryBuilder::new(
-            "INSERT INTO access_request_logs (id, host, method, path, headers, http_version, remote_addr, body_length, requested_at, website_id)",
+            "INSERT INTO access_request_logs (id, host, method, path, headers, http_version, remote_addr, body_length, requested_at, website_id, country, region)",
         );
         builder.push_values(requests.iter(), |mut b, req| {
             b.push_bind(req.id)
@@ -219,7 +251,9 @@ impl DatabaseAccessLogsModifyRepository for Database {
                 .push_bind(&req.remote_addr)
                 .push_bind(USize::from(req.body_length))
                 .push_bind(req.requested_at)
-                .push_bind(req.website_id);
+                .push_bind(req.website_id)
+                .push_bind(&req.country)
+                .push_bind(&req.region);
         });
         builder.build().execute(&self.pool).await?;
         Ok(())
@@ -232,7 +266,7 @@ impl DatabaseAccessLogsModifyRepository for Database {
             return Ok(());
         }
         let mut builder = QueryBuilder::new(
-            "INSERT INTO access_response_logs (id, status, headers, body_length, http_version, backend_responsed_at, responsed_at, website_id)",
+            "INSERT INTO access_response_logs (id, status, headers, body_length, http_version, backend_responsed_at, responsed_at, website_id, cache_status, auth_status, auth_user)",
         );
         builder.push_values(responses.iter(), |mut b, resp| {
             b.push_bind(resp.id)
@@ -242,7 +276,10 @@ impl DatabaseAccessLogsModifyRepository for Database {
                 .push_bind(resp.http_version.to_string())
                 .push_bind(resp.backend_responsed_at)
                 .push_bind(resp.responsed_at)
-                .push_bind(resp.website_id);
+                .push_bind(resp.website_id)
+                .push_bind(resp.cache_status.map(|v| v.to_string()))
+                .push_bind(resp.auth_status.map(|v| v.to_string()))
+                .push_bind(&resp.auth_user);
         });
         builder.build().execute(&self.pool).await?;
         Ok(())
@@ -356,4 +393,27 @@ impl DatabaseAccessLogsModifyRepository for Database {
         builder.build().execute(&self.pool).await?;
         Ok(())
     }
+
+    async fn insert_batch_access_attempts(
+        &self,
+        attempts: Vec<AccessCreateAttempt>,
+    ) -> anyhow::Result<()> {
+        if attempts.is_empty() {
+            return Ok(());
+        }
+        let mut builder = QueryBuilder::new(
+            "INSERT INTO access_attempt_logs (request_id, attempt, backend, status, error, elapsed, created_at)",
+        );
+        builder.push_values(attempts.iter(), |mut b, attempt| {
+            b.push_bind(attempt.request_id)
+                .push_bind(U16::from(attempt.attempt))
+                .push_bind(&attempt.backend)
+                .push_bind(attempt.status.map(U16::from))
+                .push_bind(&attempt.error)
+                .push_bind(USize::from(attempt.elapsed as usize))
+                .push_bind(attempt.created_at);
+        });
+        builder.build().execute(&self.pool).await?;
+        Ok(())
+    }
 }
@@ -62,6 +62,21 @@ pub trait DatabaseConfigurationModifyRepository {
         &self,
         config: &Configuration<T>,
     ) -> anyhow::Result<()>;
+    /// key 已经存在时不覆盖，用于多个实例同时初始化同一个值
+    async fn set_configuration_if_absent<
+        T: for<'de> Deserialize<'de> + Serialize + Clone + Send,
+    >(
+        &self,
+        key: impl Into<String> + Send,
+        value: T,
+    ) -> anyhow::Result<()> {
+        let config = Configuration::new(key.into(), value);
+        self.set_raw_configuration_if_absent(&config).await
+    }
+    async fn set_raw_configuration_if_absent<T: for<'de> Deserialize<'de> + Serialize + Clone>(
+        &self,
+        config: &Configuration<T>,
+    ) -> anyhow::Result<()>;
 }
 
 #[async_trait]
@@ -77,4 +92,16 @@ impl DatabaseConfigurationModifyRepository for Database {
             .await?;
         Ok(())
     }
+
+    async fn set_raw_configuration_if_absent<T: for<'de> Deserialize<'de> + Serialize + Clone>(
+        &self,
+        config: &Configuration<T>,
+    ) -> anyhow::Result<()> {
+        sqlx::query("INSERT INTO configurations (key, value) VALUES (LOWER($1), $2) ON CONFLICT (key) DO NOTHING")
+            .bind(config.key())
+            .bind(Json(config.get_helper_value()))
+            .execute(&self.pool)
+            .await?;
+        Ok(())
+    }
 }
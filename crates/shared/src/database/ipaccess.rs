@@ -0,0 +1,69 @@
+use async_trait::async_trait;
+
+use crate::{database::Database, models::ipaccess::DatabaseIpAccessList};
+use anyhow::Result;
+
+#[async_trait]
+pub trait DatabaseIpAccessListInitializer {
+    async fn initialize_ip_access_lists(&self) -> Result<()>;
+}
+
+#[async_trait]
+impl DatabaseIpAccessListInitializer for Database {
+    async fn initialize_ip_access_lists(&self) -> Result<()> {
+        for sql in [
+            r#"CREATE TABLE IF NOT EXISTS ip_access_lists (
+                id TEXT PRIMARY KEY,
+                name TEXT,
+                website_id TEXT,
+                mode TEXT NOT NULL DEFAULT 'deny_first',
+                allow TEXT[] NOT NULL DEFAULT '{}',
+                deny TEXT[] NOT NULL DEFAULT '{}',
+                created_at TIMESTAMPTZ NOT NULL DEFAULT NOW(),
+                updated_at TIMESTAMPTZ NOT NULL DEFAULT NOW()
+            );"#,
+            "CREATE INDEX IF NOT EXISTS idx_ip_access_lists_website_id ON ip_access_lists (website_id);",
+            // notify_change 只处理 INSERT / UPDATE，删除列表也需要通知网关
+            r#"CREATE OR REPLACE FUNCTION notify_delete()
+                RETURNS TRIGGER AS $$
+                BEGIN
+                    PERFORM pg_notify(
+                        TG_TABLE_NAME || '_updater',
+                        json_build_object('id', OLD.id, 'updated_at', NOW())::text
+                    );
+                    RETURN OLD;
+                END;
+                $$ LANGUAGE plpgsql;
+            "#,
+            "DROP TRIGGER IF EXISTS ip_access_lists_delete_notify ON ip_access_lists;",
+            r#"CREATE TRIGGER ip_access_lists_delete_notify
+                AFTER DELETE ON ip_access_lists
+                FOR EACH ROW
+                EXECUTE FUNCTION notify_delete();
+            "#,
+        ] {
+            sqlx::query(sql).execute(&self.pool).await?;
+        }
+        self.create_trigger_notify("ip_access_lists").await?;
+        Ok(())
+    }
+}
+
+#[async_trait]
+pub trait DatabaseIpAccessListRepository {
+    async fn get_ip_access_lists(&self) -> Result<Vec<DatabaseIpAccessList>>;
+}
+
+#[async_trait]
+impl DatabaseIpAccessListRepository for Database {
+    async fn get_ip_access_lists(&self) -> Result<Vec<DatabaseIpAccessList>> {
+        let rows = sqlx::query_as::<_, DatabaseIpAccessList>(
+            r#"
+            SELECT * FROM ip_access_lists ORDER BY created_at
+        "#,
+        )
+        .fetch_all(&self.pool)
+        .await?;
+        Ok(rows)
+    }
+}
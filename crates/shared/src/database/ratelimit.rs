@@ -0,0 +1,77 @@
+use async_trait::async_trait;
+
+use crate::database::Database;
+use anyhow::Result;
+
+#[async_trait]
+pub trait DatabaseRateLimitInitializer {
+    async fn initialize_rate_limits(&self) -> Result<()>;
+}
+
+#[async_trait]
+impl DatabaseRateLimitInitializer for Database {
+    async fn initialize_rate_limits(&self) -> Result<()> {
+        sqlx::query(
+            r#"
+            CREATE TABLE IF NOT EXISTS rate_limit_counters (
+                key TEXT PRIMARY KEY,
+                consumed DOUBLE PRECISION NOT NULL DEFAULT 0,
+                updated_at TIMESTAMPTZ NOT NULL DEFAULT NOW()
+            )
+        "#,
+        )
+        .execute(&self.pool)
+        .await?;
+        Ok(())
+    }
+}
+
+#[async_trait]
+pub trait DatabaseRateLimitRepository {
+    /// 累加各实例消耗的令牌，返回累加后的总数
+    async fn add_rate_limit_consumed(
+        &self,
+        consumed: Vec<(String, f64)>,
+    ) -> Result<Vec<(String, f64)>>;
+    /// 删除一段时间没有更新的计数
+    async fn clean_rate_limit_counters(&self, idle_seconds: u64) -> Result<()>;
+}
+
+#[async_trait]
+impl DatabaseRateLimitRepository for Database {
+    async fn add_rate_limit_consumed(
+        &self,
+        consumed: Vec<(String, f64)>,
+    ) -> Result<Vec<(String, f64)>> {
+        if consumed.is_empty() {
+            return Ok(vec![]);
+        }
+        let (keys, values): (Vec<_>, Vec<_>) = consumed.into_iter().unzip();
+        let rows = sqlx::query_as::<_, (String, f64)>(
+            r#"
+            INSERT INTO rate_limit_counters (key, consumed)
+            SELECT * FROM UNNEST($1::TEXT[], $2::DOUBLE PRECISION[])
+            ON CONFLICT (key) DO UPDATE
+            SET consumed = rate_limit_counters.consumed + EXCLUDED.consumed, updated_at = NOW()
+            RETURNING key, consumed
+        "#,
+        )
+        .bind(keys)
+        .bind(values)
+        .fetch_all(&self.pool)
+        .await?;
+        Ok(rows)
+    }
+
+    async fn clean_rate_limit_counters(&self, idle_seconds: u64) -> Result<()> {
+        sqlx::query(
+            r#"
+            DELETE FROM rate_limit_counters WHERE updated_at < NOW() - INTERVAL '1 second' * $1
+        "#,
+        )
+        .bind(idle_seconds as i64)
+        .execute(&self.pool)
+        .await?;
+        Ok(())
+    }
+}
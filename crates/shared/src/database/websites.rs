@@ -3,7 +3,10 @@ use sqlx_pg_ext_uint::c_u16::U16;
 
 use crate::{
     database::Database,
-    models::websites::{CreateDatabaseWebsite, DatabaseWebsite, DatabaseWebsiteConfig},
+    models::websites::{
+        CreateDatabaseWebsite, DatabaseWebsite, DatabaseWebsiteBackendHealth,
+        DatabaseWebsiteConfig,
+    },
     objectid::ObjectId,
 };
 
@@ -25,11 +28,30 @@ impl DatabaseWebsiteInitializer for Database {
                 created_at TIMESTAMPTZ NOT NULL DEFAULT NOW(),
                 updated_at TIMESTAMPTZ NOT NULL DEFAULT NOW(),
                 backends JSONB NOT NULL,
-                config JSONB NOT NULL
+                config JSONB NOT NULL,
+                routes JSONB NOT NULL DEFAULT '[]',
+                geo_backends JSONB NOT NULL DEFAULT '[]'
             );"#,
+            "ALTER TABLE websites ADD COLUMN IF NOT EXISTS routes JSONB NOT NULL DEFAULT '[]';",
+            "ALTER TABLE websites ADD COLUMN IF NOT EXISTS geo_backends JSONB NOT NULL DEFAULT '[]';",
             "CREATE INDEX IF NOT EXISTS idx_websites_hosts ON websites USING GIN (hosts);",
             "CREATE INDEX IF NOT EXISTS idx_websites_name ON websites USING GIN (name);",
             "CREATE INDEX IF NOT EXISTS idx_websites_created_at ON websites (created_at);",
+            r#"CREATE TABLE IF NOT EXISTS website_backend_health (
+                website_id TEXT NOT NULL,
+                route TEXT NOT NULL DEFAULT 'default',
+                url TEXT NOT NULL,
+                main BOOLEAN NOT NULL,
+                healthy BOOLEAN NOT NULL,
+                status uint2,
+                error TEXT,
+                checked_at TIMESTAMPTZ NOT NULL DEFAULT NOW(),
+                changed_at TIMESTAMPTZ NOT NULL DEFAULT NOW()
+            );"#,
+            "ALTER TABLE website_backend_health ADD COLUMN IF NOT EXISTS route TEXT NOT NULL DEFAULT 'default';",
+            // 不同路由可能使用相同的后端地址，旧的 (website_id, url) 主键改为带上路由的唯一索引
+            "ALTER TABLE website_backend_health DROP CONSTRAINT IF EXISTS website_backend_health_pkey;",
+            "CREATE UNIQUE INDEX IF NOT EXISTS idx_website_backend_health_key ON website_backend_health (website_id, route, url);",
         ] {
             sqlx::query(sql).execute(&self.pool).await?;
         }
@@ -46,6 +68,10 @@ pub trait DatabaseWebsiteRepository {
         updated_at: &chrono::DateTime<chrono::Utc>,
     ) -> anyhow::Result<Vec<DatabaseWebsite>>;
     async fn get_website(&self, id: &ObjectId) -> anyhow::Result<DatabaseWebsite>;
+    async fn get_website_backends_health(
+        &self,
+        website_id: Option<&ObjectId>,
+    ) -> anyhow::Result<Vec<DatabaseWebsiteBackendHealth>>;
 }
 
 #[async_trait::async_trait]
@@ -77,6 +103,19 @@ impl DatabaseWebsiteRepository for Database {
             .await?;
         Ok(row)
     }
+
+    async fn get_website_backends_health(
+        &self,
+        website_id: Option<&ObjectId>,
+    ) -> anyhow::Result<Vec<DatabaseWebsiteBackendHealth>> {
+        let rows = sqlx::query_as::<_, DatabaseWebsiteBackendHealth>(
+            "SELECT * FROM website_backend_health WHERE $1::TEXT IS NULL OR website_id = $1 ORDER BY website_id, route, main DESC, url;",
+        )
+        .bind(website_id)
+        .fetch_all(&self.pool)
+        .await?;
+        Ok(rows)
+    }
 }
 
 #[async_trait::async_trait]
@@ -85,6 +124,10 @@ pub trait DatabaseWebsiteModifyRepository {
         &self,
         website: &CreateDatabaseWebsite,
     ) -> anyhow::Result<DatabaseWebsite>;
+    async fn update_website_backend_health(
+        &self,
+        health: &DatabaseWebsiteBackendHealth,
+    ) -> anyhow::Result<()>;
 }
 
 #[async_trait::async_trait]
@@ -94,7 +137,7 @@ impl DatabaseWebsiteModifyRepository for Database {
         website: &CreateDatabaseWebsite,
     ) -> anyhow::Result<DatabaseWebsite> {
         let id = ObjectId::new();
-        let row = sqlx::query_as::<_, _>("INSERT INTO websites (id, name, hosts, ports, certificates, backends, config) VALUES ($1, $2, $3, $4, $5, $6, $7) RETURNING *;")
+        let row = sqlx::query_as::<_, _>("INSERT INTO websites (id, name, hosts, ports, certificates, backends, config, routes, geo_backends) VALUES ($1, $2, $3, $4, $5, $6, $7, $8, $9) RETURNING *;")
             .bind(id)
             .bind(website.name.as_ref())
             .bind(website.hosts.to_vec())
@@ -102,8 +145,34 @@ impl DatabaseWebsiteModifyRepository for Database {
             .bind(website.certificates.to_vec())
             .bind(Json(&website.backends.to_vec()))
             .bind(Json(website.config.as_ref().unwrap_or(&DatabaseWebsiteConfig::default())))
+            .bind(Json(&website.routes))
+            .bind(Json(&website.geo_backends))
             .fetch_one(&self.pool)
             .await?;
         Ok(row)
     }
+
+    async fn update_website_backend_health(
+        &self,
+        health: &DatabaseWebsiteBackendHealth,
+    ) -> anyhow::Result<()> {
+        sqlx::query(
+            r#"INSERT INTO website_backend_health (website_id, route, url, main, healthy, status, error, checked_at, changed_at)
+            VALUES ($1, $2, $3, $4, $5, $6, $7, $8, $9)
+            ON CONFLICT (website_id, route, url) DO UPDATE SET
+                main = $4, healthy = $5, status = $6, error = $7, checked_at = $8, changed_at = $9;"#,
+        )
+        .bind(health.website_id)
+        .bind(&health.route)
+        .bind(health.url.as_str())
+        .bind(health.main)
+        .bind(health.healthy)
+        .bind(health.status.map(U16::from))
+        .bind(health.error.as_ref())
+        .bind(health.checked_at)
+        .bind(health.changed_at)
+        .execute(&self.pool)
+        .await?;
+        Ok(())
+    }
 }
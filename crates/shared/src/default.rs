@@ -3,10 +3,52 @@ use std::time::Duration;
 use rcgen::{CertifiedKey, generate_simple_self_signed};
 use rustls::pki_types::{CertificateDer, PrivateKeyDer, PrivatePkcs8KeyDer};
 
+use crate::models::websites::DatabaseWebsiteCompressionAlgorithm;
+
 pub fn default_website_config_timeout() -> Duration {
     Duration::from_secs(10)
 }
 
+pub fn default_website_timeout_connect() -> u64 {
+    default_website_config_timeout().as_secs()
+}
+
+pub fn default_website_timeout_header() -> u64 {
+    60
+}
+
+pub fn default_health_check_path() -> String {
+    "/".to_string()
+}
+
+pub fn default_health_check_interval() -> u64 {
+    10
+}
+
+pub fn default_health_check_timeout() -> u64 {
+    5
+}
+
+pub fn default_health_check_rise() -> usize {
+    2
+}
+
+pub fn default_health_check_fall() -> usize {
+    3
+}
+
+pub fn default_outlier_detection_consecutive_failures() -> usize {
+    5
+}
+
+pub fn default_outlier_detection_ejection_time() -> u64 {
+    30
+}
+
+pub fn default_outlier_detection_max_ejection_time() -> u64 {
+    300
+}
+
 pub fn default_dashboard_api_port() -> u16 {
     3000
 }
@@ -42,3 +84,133 @@ pub fn reqwest_default_client() -> reqwest::Client {
         .build()
         .unwrap()
 }
+
+pub fn default_connection_pool_idle_timeout() -> u64 {
+    90
+}
+
+pub fn default_connection_pool_max_idle_per_host() -> usize {
+    32
+}
+
+pub fn default_upgrade_idle_timeout() -> u64 {
+    300
+}
+
+pub fn default_retry_max_attempts() -> usize {
+    1
+}
+
+pub fn default_retry_status() -> Vec<u16> {
+    vec![502, 503, 504]
+}
+
+pub fn default_retry_budget_ratio() -> f64 {
+    0.2
+}
+
+pub fn default_retry_budget_min_per_second() -> usize {
+    3
+}
+
+pub fn default_retry_max_replay_body() -> usize {
+    64 * 1024
+}
+
+pub fn default_server_header() -> Option<String> {
+    Some("WebGateway".to_string())
+}
+
+pub fn default_force_https_status() -> u16 {
+    308
+}
+
+pub fn default_force_https_port() -> u16 {
+    443
+}
+
+pub fn default_redirect_status() -> u16 {
+    301
+}
+
+pub fn default_static_files_index() -> Vec<String> {
+    vec!["index.html".to_string()]
+}
+
+pub fn default_compression_algorithms() -> Vec<DatabaseWebsiteCompressionAlgorithm> {
+    vec![
+        DatabaseWebsiteCompressionAlgorithm::Zstd,
+        DatabaseWebsiteCompressionAlgorithm::Brotli,
+        DatabaseWebsiteCompressionAlgorithm::Gzip,
+    ]
+}
+
+pub fn default_compression_gzip_level() -> i32 {
+    6
+}
+
+pub fn default_compression_brotli_level() -> i32 {
+    4
+}
+
+pub fn default_compression_zstd_level() -> i32 {
+    3
+}
+
+pub fn default_compression_min_size() -> u64 {
+    1024
+}
+
+/// 不包含 text/event-stream，压缩会缓冲事件流
+pub fn default_compression_mime_types() -> Vec<String> {
+    [
+        "text/html",
+        "text/css",
+        "text/plain",
+        "text/xml",
+        "text/javascript",
+        "application/javascript",
+        "application/json",
+        "application/xml",
+        "application/wasm",
+        "image/svg+xml",
+    ]
+    .into_iter()
+    .map(|v| v.to_string())
+    .collect()
+}
+
+pub fn default_cache_max_memory() -> u64 {
+    64 * 1024 * 1024
+}
+
+pub fn default_cache_max_object_size() -> u64 {
+    1024 * 1024
+}
+
+pub fn default_cache_max_disk() -> u64 {
+    1024 * 1024 * 1024
+}
+
+pub fn default_rate_limit_period() -> u64 {
+    1
+}
+
+pub fn default_geo_challenge_ttl() -> u64 {
+    3600
+}
+
+pub fn default_basic_auth_realm() -> String {
+    "Restricted".to_string()
+}
+
+pub fn default_forward_auth_request_headers() -> Vec<String> {
+    ["Cookie", "Authorization"]
+        .into_iter()
+        .map(|v| v.to_string())
+        .collect()
+}
+
+pub fn default_forward_auth_timeout() -> u64 {
+    5
+}
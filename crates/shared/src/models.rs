@@ -2,4 +2,5 @@ pub mod access;
 pub mod certificate;
 pub mod configuration;
 pub mod dnsprovider;
+pub mod ipaccess;
 pub mod websites;
@@ -92,6 +92,8 @@ pub struct AccessRequest {
     pub created_at: DateTime<Utc>,
     pub requested_at: DateTime<Utc>,
     pub website_id: Option<ObjectId>,
+    pub country: Option<String>,
+    pub region: Option<String>,
 }
 
 impl<'r> FromRow<'r, PgRow> for AccessRequest {
@@ -108,6 +110,8 @@ impl<'r> FromRow<'r, PgRow> for AccessRequest {
             body_length: row.try_get::<USize, _>("body_length")?.into(),
             host: row.try_get("host")?,
             website_id: row.try_get("website_id")?,
+            country: row.try_get("country")?,
+            region: row.try_get("region")?,
         })
     }
 }
@@ -124,6 +128,10 @@ pub struct AccessCreateRequest {
     pub body_length: usize,
     pub requested_at: DateTime<Utc>,
     pub website_id: Option<ObjectId>,
+    /// 请求时解析出的国家代码，如 CN
+    pub country: Option<String>,
+    /// 国家内的地区代码，如 GD
+    pub region: Option<String>,
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
@@ -167,6 +175,56 @@ pub struct AccessCreateResponse {
     pub responsed_at: DateTime<Utc>,
     pub backend_responsed_at: Option<DateTime<Utc>>,
     pub website_id: Option<ObjectId>,
+    /// 未启用缓存时为 None
+    pub cache_status: Option<AccessCacheStatus>,
+    /// 未启用 Basic 认证或请求被排除时为 None
+    pub auth_status: Option<AccessAuthStatus>,
+    /// 客户端提供的用户名，认证失败时也会记录
+    pub auth_user: Option<String>,
+}
+
+/// 网关缓存的处理结果
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum AccessCacheStatus {
+    Hit,
+    Miss,
+    /// 返回过期的缓存，同时在后台刷新
+    Stale,
+    /// 向后端验证后继续使用缓存
+    Revalidated,
+}
+
+impl std::fmt::Display for AccessCacheStatus {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            AccessCacheStatus::Hit => write!(f, "hit"),
+            AccessCacheStatus::Miss => write!(f, "miss"),
+            AccessCacheStatus::Stale => write!(f, "stale"),
+            AccessCacheStatus::Revalidated => write!(f, "revalidated"),
+        }
+    }
+}
+
+/// 网关 Basic 认证的结果
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum AccessAuthStatus {
+    Success,
+    /// 没有提供凭据
+    Missing,
+    /// 凭据格式错误、用户不存在或密码错误
+    Failed,
+}
+
+impl std::fmt::Display for AccessAuthStatus {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            AccessAuthStatus::Success => write!(f, "success"),
+            AccessAuthStatus::Missing => write!(f, "missing"),
+            AccessAuthStatus::Failed => write!(f, "failed"),
+        }
+    }
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
@@ -306,6 +364,19 @@ pub struct AccessInsertResponseSize {
     pub created_at: DateTime<Utc>,
 }
 
+/// 发往后端的一次尝试（只记录发生过重试的请求）
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct AccessCreateAttempt {
+    pub request_id: ObjectId,
+    pub attempt: u16,
+    pub backend: String,
+    pub status: Option<u16>,
+    pub error: Option<String>,
+    /// 毫秒
+    pub elapsed: u64,
+    pub created_at: DateTime<Utc>,
+}
+
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct AccessInsertRequestSize {
     pub id: ObjectId,
@@ -334,7 +405,6 @@ impl AccessInsertResponseSize {
     }
 }
 
-
 // Website Access Info
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct WebsiteAccessInfo {
@@ -373,12 +443,10 @@ impl<'r> FromRow<'r, PgRow> for TodayMetricsInfoOfWebsite {
             e5xx_requests: row.try_get::<i64, _>("e5xx_requests")? as usize,
             total_requests_size: row.try_get::<USize, _>("total_requests_size")?.into(),
             total_response_size: row.try_get::<USize, _>("total_response_size")?.into(),
-        }
-        )
+        })
     }
 }
 
-
 #[derive(Debug, Clone, Serialize, Default)]
 pub enum QueryAccessMapType {
     #[default]
@@ -422,4 +490,4 @@ pub struct QueryAccessMap {
     pub in_days: QueryAccessInfoDays,
     #[serde(rename = "type")]
     pub map_type: QueryAccessMapType,
-}
\ No newline at end of file
+}
@@ -0,0 +1,65 @@
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use sqlx::{Error, FromRow, Row, postgres::PgRow, types::Text};
+
+use crate::objectid::ObjectId;
+
+/// IP 访问控制列表，website_id 为空时对所有网站生效
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct DatabaseIpAccessList {
+    pub id: ObjectId,
+    pub name: Option<String>,
+    pub website_id: Option<ObjectId>,
+    pub mode: DatabaseIpAccessMode,
+    /// IPv4 / IPv6 CIDR，也可以是单个地址
+    pub allow: Vec<String>,
+    pub deny: Vec<String>,
+    pub created_at: DateTime<Utc>,
+    pub updated_at: DateTime<Utc>,
+}
+
+impl<'r> FromRow<'r, PgRow> for DatabaseIpAccessList {
+    fn from_row(row: &PgRow) -> Result<Self, Error> {
+        Ok(DatabaseIpAccessList {
+            id: row.try_get("id")?,
+            name: row.try_get("name")?,
+            website_id: row.try_get("website_id")?,
+            mode: row.try_get::<Text<DatabaseIpAccessMode>, _>("mode")?.0,
+            allow: row.try_get("allow")?,
+            deny: row.try_get("deny")?,
+            created_at: row.try_get("created_at")?,
+            updated_at: row.try_get("updated_at")?,
+        })
+    }
+}
+
+/// 地址同时在 allow 和 deny 中时先匹配的生效，
+/// 都不匹配时 allow 不为空则拒绝（白名单），否则放行
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize, Default)]
+#[serde(rename_all = "snake_case")]
+pub enum DatabaseIpAccessMode {
+    AllowFirst,
+    #[default]
+    DenyFirst,
+}
+
+impl std::str::FromStr for DatabaseIpAccessMode {
+    type Err = std::fmt::Error;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "allow_first" => Ok(DatabaseIpAccessMode::AllowFirst),
+            "deny_first" => Ok(DatabaseIpAccessMode::DenyFirst),
+            _ => Err(std::fmt::Error),
+        }
+    }
+}
+
+impl std::fmt::Display for DatabaseIpAccessMode {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            DatabaseIpAccessMode::AllowFirst => write!(f, "allow_first"),
+            DatabaseIpAccessMode::DenyFirst => write!(f, "deny_first"),
+        }
+    }
+}
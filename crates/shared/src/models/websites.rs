@@ -1,10 +1,33 @@
+use std::collections::HashMap;
+
 use chrono::{DateTime, Utc};
 use serde::{Deserialize, Serialize};
 use sqlx::{Error, FromRow, Row, postgres::PgRow, types::Json};
 use sqlx_pg_ext_uint::c_u16::U16;
 use url::Url;
+use utils::default_true;
 
-use crate::objectid::ObjectId;
+use crate::{
+    default::{
+        default_basic_auth_realm, default_cache_max_disk, default_cache_max_memory,
+        default_cache_max_object_size, default_compression_algorithms,
+        default_compression_brotli_level, default_compression_gzip_level,
+        default_compression_mime_types, default_compression_min_size,
+        default_compression_zstd_level, default_connection_pool_idle_timeout,
+        default_connection_pool_max_idle_per_host, default_force_https_port,
+        default_force_https_status, default_forward_auth_request_headers,
+        default_forward_auth_timeout, default_geo_challenge_ttl, default_health_check_fall,
+        default_health_check_interval, default_health_check_path, default_health_check_rise,
+        default_health_check_timeout, default_outlier_detection_consecutive_failures,
+        default_outlier_detection_ejection_time, default_outlier_detection_max_ejection_time,
+        default_rate_limit_period, default_redirect_status, default_retry_budget_min_per_second,
+        default_retry_budget_ratio, default_retry_max_attempts, default_retry_max_replay_body,
+        default_retry_status, default_server_header, default_static_files_index,
+        default_upgrade_idle_timeout, default_website_timeout_connect,
+        default_website_timeout_header,
+    },
+    objectid::ObjectId,
+};
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct DatabaseWebsite {
@@ -17,6 +40,11 @@ pub struct DatabaseWebsite {
     pub updated_at: DateTime<Utc>,
     pub backends: Vec<DatabaseWebsiteBackend>,
     pub config: DatabaseWebsiteConfig,
+    /// 按顺序匹配，都不匹配时使用 backends / config
+    pub routes: Vec<DatabaseWebsiteRoute>,
+    /// 按客户端位置选择的后端组，都不匹配或组内都不可用时使用 backends，
+    /// 因此配置后 backends 不能为空
+    pub geo_backends: Vec<DatabaseWebsiteGeoBackends>,
 }
 
 impl<'r> FromRow<'r, PgRow> for DatabaseWebsite {
@@ -37,20 +65,673 @@ impl<'r> FromRow<'r, PgRow> for DatabaseWebsite {
                 .try_get::<Json<Vec<DatabaseWebsiteBackend>>, _>("backends")?
                 .0,
             config: row.try_get::<Json<DatabaseWebsiteConfig>, _>("config")?.0,
+            routes: row
+                .try_get::<Json<Vec<DatabaseWebsiteRoute>>, _>("routes")?
+                .0,
+            geo_backends: row
+                .try_get::<Json<Vec<DatabaseWebsiteGeoBackends>>, _>("geo_backends")?
+                .0,
         })
     }
 }
 
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct DatabaseWebsiteRoute {
+    #[serde(default)]
+    pub name: Option<String>,
+    pub path: DatabaseWebsiteRoutePath,
+    /// 为空时匹配任意方法
+    #[serde(default)]
+    pub methods: Vec<String>,
+    /// 全部满足才匹配
+    #[serde(default)]
+    pub headers: Vec<DatabaseWebsiteRouteHeader>,
+    pub backends: Vec<DatabaseWebsiteBackend>,
+    /// 为空时沿用网站的配置
+    #[serde(default)]
+    pub config: Option<DatabaseWebsiteConfig>,
+    #[serde(default)]
+    pub rewrite: Option<DatabaseWebsiteRewrite>,
+    /// 按客户端位置选择的后端组，都不匹配或组内都不可用时使用 backends，
+    /// 因此配置后 backends 不能为空
+    #[serde(default)]
+    pub geo_backends: Vec<DatabaseWebsiteGeoBackends>,
+}
+
+/// 客户端位于指定国家或地区时使用的后端组
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct DatabaseWebsiteGeoBackends {
+    /// 国家代码，如 CN、US
+    #[serde(default)]
+    pub countries: Vec<String>,
+    /// 国家代码加地区代码，如 CN-GD
+    #[serde(default)]
+    pub regions: Vec<String>,
+    pub backends: Vec<DatabaseWebsiteBackend>,
+}
+
+/// 转发前改写路径和参数，路径依次执行 strip_prefix、regex、add_prefix
+#[derive(Debug, Clone, Serialize, Deserialize, Default)]
+pub struct DatabaseWebsiteRewrite {
+    #[serde(default)]
+    pub strip_prefix: Option<String>,
+    #[serde(default)]
+    pub regex: Vec<DatabaseWebsiteRewriteRegex>,
+    #[serde(default)]
+    pub add_prefix: Option<String>,
+    /// 已存在同名参数时覆盖
+    #[serde(default)]
+    pub query_add: Vec<DatabaseWebsiteQueryParam>,
+    #[serde(default)]
+    pub query_remove: Vec<String>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct DatabaseWebsiteRewriteRegex {
+    pub pattern: String,
+    /// 支持 $1、${name} 引用捕获组
+    pub replacement: String,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct DatabaseWebsiteQueryParam {
+    pub name: String,
+    pub value: String,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase", tag = "type", content = "data")]
+pub enum DatabaseWebsiteRoutePath {
+    Exact(String),
+    /// 按路径段匹配，/api 匹配 /api 和 /api/users，不匹配 /apis
+    Prefix(String),
+    Regex(String),
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct DatabaseWebsiteRouteHeader {
+    pub name: String,
+    /// 为空时只要求请求头存在
+    #[serde(default)]
+    pub value: Option<String>,
+}
+
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct DatabaseWebsiteBackend {
     pub url: Url,
     pub balance: usize,
     pub main: bool,
+    /// 覆盖发往后端的 Host 头，为空时沿用客户端的 Host
+    #[serde(default)]
+    pub host: Option<String>,
+    /// 仅在 https:// 后端生效
+    #[serde(default)]
+    pub tls: DatabaseWebsiteBackendTls,
+    #[serde(default)]
+    pub protocol: DatabaseWebsiteBackendProtocol,
+    /// 仅在 file:// 后端生效
+    #[serde(default)]
+    pub files: DatabaseWebsiteStaticFiles,
 }
 
-#[derive(Debug, Clone, Serialize, Deserialize, Default)]
+/// file:// 后端直接由网关返回目录下的文件
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct DatabaseWebsiteStaticFiles {
+    /// 请求目录时依次尝试的文件
+    #[serde(default = "default_static_files_index")]
+    pub index: Vec<String>,
+    /// 文件不存在时返回根目录的 index.html（单页应用）
+    #[serde(default)]
+    pub spa_fallback: bool,
+    /// 目录下没有 index 文件时列出目录内容
+    #[serde(default)]
+    pub listing: bool,
+    /// 客户端支持时优先返回同名的 .br / .gz 文件
+    #[serde(default)]
+    pub precompressed: bool,
+}
+
+impl Default for DatabaseWebsiteStaticFiles {
+    fn default() -> Self {
+        Self {
+            index: default_static_files_index(),
+            spa_fallback: false,
+            listing: false,
+            precompressed: false,
+        }
+    }
+}
+
+/// 发往后端使用的协议
+#[derive(Debug, Clone, Copy, Serialize, Deserialize, Default, PartialEq, Eq)]
+#[serde(rename_all = "lowercase")]
+pub enum DatabaseWebsiteBackendProtocol {
+    #[default]
+    Http1,
+    /// https:// 后端通过 ALPN 协商，服务端不支持时回退到 HTTP/1.1
+    Http2,
+    /// 明文 HTTP/2（prior knowledge）
+    H2c,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct DatabaseWebsiteBackendTls {
+    /// 为空时使用 url 中的主机名
+    #[serde(default)]
+    pub sni: Option<String>,
+    #[serde(default = "default_true")]
+    pub verify: bool,
+    /// PEM 格式的 CA 证书，设置后只信任这些证书
+    #[serde(default)]
+    pub ca: Option<String>,
+}
+
+impl Default for DatabaseWebsiteBackendTls {
+    fn default() -> Self {
+        Self {
+            sni: None,
+            verify: true,
+            ca: None,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct DatabaseWebsiteConfig {
     pub get_request_ip: DatabaseWebsiteRequestIp,
+    #[serde(default)]
+    pub health_check: Option<DatabaseWebsiteHealthCheck>,
+    #[serde(default)]
+    pub outlier_detection: DatabaseWebsiteOutlierDetection,
+    #[serde(default)]
+    pub connection_pool: DatabaseWebsiteConnectionPool,
+    /// WebSocket 等升级连接的空闲超时，单位为秒
+    #[serde(default = "default_upgrade_idle_timeout")]
+    pub upgrade_idle_timeout: u64,
+    #[serde(default)]
+    pub timeout: DatabaseWebsiteTimeout,
+    #[serde(default)]
+    pub retry: DatabaseWebsiteRetry,
+    #[serde(default)]
+    pub headers: DatabaseWebsiteHeaders,
+    #[serde(default)]
+    pub redirect: DatabaseWebsiteRedirect,
+    #[serde(default)]
+    pub error_pages: DatabaseWebsiteErrorPages,
+    #[serde(default)]
+    pub compression: DatabaseWebsiteCompression,
+    #[serde(default)]
+    pub cache: DatabaseWebsiteCache,
+    /// 按顺序检查，任意一条超出时拒绝请求
+    #[serde(default)]
+    pub rate_limits: Vec<DatabaseWebsiteRateLimit>,
+    #[serde(default)]
+    pub geo: DatabaseWebsiteGeo,
+    #[serde(default)]
+    pub basic_auth: DatabaseWebsiteBasicAuth,
+    #[serde(default)]
+    pub forward_auth: Option<DatabaseWebsiteForwardAuth>,
+}
+
+impl Default for DatabaseWebsiteConfig {
+    fn default() -> Self {
+        Self {
+            get_request_ip: DatabaseWebsiteRequestIp::default(),
+            health_check: None,
+            outlier_detection: DatabaseWebsiteOutlierDetection::default(),
+            connection_pool: DatabaseWebsiteConnectionPool::default(),
+            upgrade_idle_timeout: default_upgrade_idle_timeout(),
+            timeout: DatabaseWebsiteTimeout::default(),
+            retry: DatabaseWebsiteRetry::default(),
+            headers: DatabaseWebsiteHeaders::default(),
+            redirect: DatabaseWebsiteRedirect::default(),
+            error_pages: DatabaseWebsiteErrorPages::default(),
+            compression: DatabaseWebsiteCompression::default(),
+            cache: DatabaseWebsiteCache::default(),
+            rate_limits: vec![],
+            geo: DatabaseWebsiteGeo::default(),
+            basic_auth: DatabaseWebsiteBasicAuth::default(),
+            forward_auth: None,
+        }
+    }
+}
+
+/// 请求超时，单位为秒，total / idle 为 0 时不限制
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct DatabaseWebsiteTimeout {
+    /// 与后端建立连接（含 TLS 握手）
+    #[serde(default = "default_website_timeout_connect")]
+    pub connect: u64,
+    /// 请求发出到收到响应头
+    #[serde(default = "default_website_timeout_header")]
+    pub header: u64,
+    /// 整个请求，包括请求和响应 body 的传输
+    #[serde(default)]
+    pub total: u64,
+    /// 请求或响应 body 两次数据之间的最长间隔
+    #[serde(default)]
+    pub idle: u64,
+}
+
+impl Default for DatabaseWebsiteTimeout {
+    fn default() -> Self {
+        Self {
+            connect: default_website_timeout_connect(),
+            header: default_website_timeout_header(),
+            total: 0,
+            idle: 0,
+        }
+    }
+}
+
+/// 主动健康检查，interval / timeout 单位为秒
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct DatabaseWebsiteHealthCheck {
+    #[serde(default = "default_health_check_path")]
+    pub path: String,
+    /// 为空时接受任意 2xx
+    #[serde(default)]
+    pub expected_status: Vec<u16>,
+    #[serde(default = "default_health_check_interval")]
+    pub interval: u64,
+    #[serde(default = "default_health_check_timeout")]
+    pub timeout: u64,
+    #[serde(default = "default_health_check_rise")]
+    pub rise: usize,
+    #[serde(default = "default_health_check_fall")]
+    pub fall: usize,
+}
+
+impl DatabaseWebsiteHealthCheck {
+    pub fn is_expected_status(&self, status: u16) -> bool {
+        if self.expected_status.is_empty() {
+            return (200..300).contains(&status);
+        }
+        self.expected_status.contains(&status)
+    }
+}
+
+/// 被动异常检测，ejection_time / max_ejection_time 单位为秒
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct DatabaseWebsiteOutlierDetection {
+    #[serde(default = "default_true")]
+    pub enabled: bool,
+    #[serde(default = "default_outlier_detection_consecutive_failures")]
+    pub consecutive_failures: usize,
+    #[serde(default = "default_outlier_detection_ejection_time")]
+    pub ejection_time: u64,
+    #[serde(default = "default_outlier_detection_max_ejection_time")]
+    pub max_ejection_time: u64,
+}
+
+impl Default for DatabaseWebsiteOutlierDetection {
+    fn default() -> Self {
+        Self {
+            enabled: true,
+            consecutive_failures: default_outlier_detection_consecutive_failures(),
+            ejection_time: default_outlier_detection_ejection_time(),
+            max_ejection_time: default_outlier_detection_max_ejection_time(),
+        }
+    }
+}
+
+/// 后端连接池，idle_timeout 单位为秒，max_connections 为 0 时不限制
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct DatabaseWebsiteConnectionPool {
+    #[serde(default = "default_connection_pool_idle_timeout")]
+    pub idle_timeout: u64,
+    #[serde(default = "default_connection_pool_max_idle_per_host")]
+    pub max_idle_per_host: usize,
+    #[serde(default)]
+    pub max_connections: usize,
+}
+
+impl Default for DatabaseWebsiteConnectionPool {
+    fn default() -> Self {
+        Self {
+            idle_timeout: default_connection_pool_idle_timeout(),
+            max_idle_per_host: default_connection_pool_max_idle_per_host(),
+            max_connections: 0,
+        }
+    }
+}
+
+/// 失败重试，max_attempts 为 1 时不重试，per_try_timeout 单位为秒（0 时使用 timeout.header）。
+/// 连接失败时请求尚未发出，任何方法都可以重试；其余情况只重试幂等方法且 body 可重放
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct DatabaseWebsiteRetry {
+    #[serde(default = "default_retry_max_attempts")]
+    pub max_attempts: usize,
+    #[serde(default = "default_true")]
+    pub connect_failure: bool,
+    /// 连接被重置或响应头超时
+    #[serde(default = "default_true")]
+    pub reset: bool,
+    #[serde(default = "default_retry_status")]
+    pub status: Vec<u16>,
+    #[serde(default)]
+    pub per_try_timeout: u64,
+    /// 重试次数最多占请求数的比例
+    #[serde(default = "default_retry_budget_ratio")]
+    pub budget_ratio: f64,
+    /// 请求较少时每秒至少允许的重试次数
+    #[serde(default = "default_retry_budget_min_per_second")]
+    pub budget_min_per_second: usize,
+    /// 超过该大小的请求 body 不缓存，也就不会重放
+    #[serde(default = "default_retry_max_replay_body")]
+    pub max_replay_body: usize,
+}
+
+impl Default for DatabaseWebsiteRetry {
+    fn default() -> Self {
+        Self {
+            max_attempts: default_retry_max_attempts(),
+            connect_failure: true,
+            reset: true,
+            status: default_retry_status(),
+            per_try_timeout: 0,
+            budget_ratio: default_retry_budget_ratio(),
+            budget_min_per_second: default_retry_budget_min_per_second(),
+            max_replay_body: default_retry_max_replay_body(),
+        }
+    }
+}
+
+/// 请求和响应头的改写规则，按顺序执行
+///
+/// value 支持变量：${client_ip}、${request_id}、${host}、${scheme}、${tls_version}、${sni}
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct DatabaseWebsiteHeaders {
+    /// 发往后端的请求头，在 X-Forwarded-* 等默认请求头之后执行
+    #[serde(default)]
+    pub request: Vec<DatabaseWebsiteHeaderRule>,
+    #[serde(default)]
+    pub response: Vec<DatabaseWebsiteHeaderRule>,
+    /// 替换响应的 Server，为 None 时不返回 Server
+    #[serde(default = "default_server_header")]
+    pub server: Option<String>,
+}
+
+impl Default for DatabaseWebsiteHeaders {
+    fn default() -> Self {
+        Self {
+            request: vec![],
+            response: vec![],
+            server: default_server_header(),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(tag = "action", rename_all = "lowercase")]
+pub enum DatabaseWebsiteHeaderRule {
+    /// 覆盖同名的头
+    Set {
+        name: String,
+        value: String,
+    },
+    Append {
+        name: String,
+        value: String,
+    },
+    Remove {
+        name: String,
+    },
+}
+
+/// 在请求后端之前执行，先检查 force_https，再按顺序匹配 rules
+#[derive(Debug, Clone, Serialize, Deserialize, Default)]
+pub struct DatabaseWebsiteRedirect {
+    #[serde(default)]
+    pub force_https: Option<DatabaseWebsiteForceHttps>,
+    #[serde(default)]
+    pub rules: Vec<DatabaseWebsiteRedirectRule>,
+}
+
+/// 明文请求跳转到 TLS 端口，保留路径和参数
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct DatabaseWebsiteForceHttps {
+    #[serde(default = "default_force_https_status")]
+    pub status: u16,
+    #[serde(default = "default_force_https_port")]
+    pub port: u16,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct DatabaseWebsiteRedirectRule {
+    /// 匹配的 Host（不含端口），为空时不限制；host 和 path 至少需要一个
+    #[serde(default)]
+    pub host: Option<String>,
+    /// 匹配路径的正则，为空时不限制
+    #[serde(default)]
+    pub path: Option<String>,
+    /// 以下为空时保持原请求的值
+    #[serde(default)]
+    pub to_scheme: Option<String>,
+    #[serde(default)]
+    pub to_host: Option<String>,
+    /// 支持 $1、${name} 引用 path 的捕获组
+    #[serde(default)]
+    pub to_path: Option<String>,
+    #[serde(default = "default_redirect_status")]
+    pub status: u16,
+    #[serde(default = "default_true")]
+    pub preserve_query: bool,
+}
+
+/// 网关自身生成的错误响应（404、504 等）使用的页面，
+/// 模板中可以使用 ${{ status }}、${{ title }}、${{ message }}、${{ request_id }}
+#[derive(Debug, Clone, Serialize, Deserialize, Default)]
+pub struct DatabaseWebsiteErrorPages {
+    /// 所有状态码共用的模板，为空时使用内置页面
+    #[serde(default)]
+    pub template: Option<String>,
+    /// 按状态码覆盖 template
+    #[serde(default)]
+    pub pages: HashMap<u16, String>,
+}
+
+/// 按客户端的 Accept-Encoding 压缩响应，后端已压缩的响应不会重复压缩
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct DatabaseWebsiteCompression {
+    #[serde(default)]
+    pub enabled: bool,
+    /// 客户端的权重相同时按此顺序选择
+    #[serde(default = "default_compression_algorithms")]
+    pub algorithms: Vec<DatabaseWebsiteCompressionAlgorithm>,
+    #[serde(default = "default_compression_gzip_level")]
+    pub gzip_level: i32,
+    #[serde(default = "default_compression_brotli_level")]
+    pub brotli_level: i32,
+    #[serde(default = "default_compression_zstd_level")]
+    pub zstd_level: i32,
+    /// 小于此大小（字节）的响应不压缩，长度未知时总是压缩
+    #[serde(default = "default_compression_min_size")]
+    pub min_size: u64,
+    /// 允许压缩的 Content-Type，支持 `text/*`
+    #[serde(default = "default_compression_mime_types")]
+    pub mime_types: Vec<String>,
+}
+
+impl Default for DatabaseWebsiteCompression {
+    fn default() -> Self {
+        Self {
+            enabled: false,
+            algorithms: default_compression_algorithms(),
+            gzip_level: default_compression_gzip_level(),
+            brotli_level: default_compression_brotli_level(),
+            zstd_level: default_compression_zstd_level(),
+            min_size: default_compression_min_size(),
+            mime_types: default_compression_mime_types(),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum DatabaseWebsiteCompressionAlgorithm {
+    Zstd,
+    #[serde(rename = "br")]
+    Brotli,
+    Gzip,
+}
+
+/// 缓存后端响应，遵循 Cache-Control / Expires / Vary，大小单位为字节，时间单位为秒
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct DatabaseWebsiteCache {
+    #[serde(default)]
+    pub enabled: bool,
+    #[serde(default = "default_cache_max_memory")]
+    pub max_memory: u64,
+    /// 超过此大小的响应不缓存
+    #[serde(default = "default_cache_max_object_size")]
+    pub max_object_size: u64,
+    /// 内存淘汰的对象写入此目录下按网站和路由区分的子目录，为空时只使用内存；
+    /// 子目录在重新加载配置后保留，关闭缓存时删除
+    #[serde(default)]
+    pub disk_path: Option<String>,
+    #[serde(default = "default_cache_max_disk")]
+    pub max_disk: u64,
+    /// 后端没有给出过期时间时使用，为 0 时不缓存
+    #[serde(default)]
+    pub default_ttl: u64,
+    /// 过期后仍可直接返回并在后台刷新的时间，后端的 stale-while-revalidate 优先
+    #[serde(default)]
+    pub stale_while_revalidate: u64,
+}
+
+impl Default for DatabaseWebsiteCache {
+    fn default() -> Self {
+        Self {
+            enabled: false,
+            max_memory: default_cache_max_memory(),
+            max_object_size: default_cache_max_object_size(),
+            disk_path: None,
+            max_disk: default_cache_max_disk(),
+            default_ttl: 0,
+            stale_while_revalidate: 0,
+        }
+    }
+}
+
+/// 令牌桶限流，超出时返回 429
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct DatabaseWebsiteRateLimit {
+    #[serde(default)]
+    pub key: DatabaseWebsiteRateLimitKey,
+    /// 每个周期补充的请求数
+    pub requests: u64,
+    /// 补充周期，单位为秒
+    #[serde(default = "default_rate_limit_period")]
+    pub period: u64,
+    /// 桶的容量（允许的突发请求数），为 0 时等于 requests
+    #[serde(default)]
+    pub burst: u64,
+    /// 通过数据库在多个网关实例之间共享计数
+    #[serde(default)]
+    pub shared: bool,
+}
+
+/// 按什么区分客户端，请求没有对应的头部时不限流
+#[derive(Debug, Clone, Serialize, Deserialize, Default)]
+#[serde(rename_all = "camelCase", tag = "type", content = "data")]
+pub enum DatabaseWebsiteRateLimitKey {
+    #[default]
+    Ip,
+    Header(String),
+    Path,
+}
+
+/// 按客户端 IP 所在的国家和地区放行、拒绝或要求通过 JS 验证
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct DatabaseWebsiteGeo {
+    /// 按顺序检查，第一条命中的规则生效
+    #[serde(default)]
+    pub rules: Vec<DatabaseWebsiteGeoRule>,
+    /// 没有规则命中或无法解析位置时的动作
+    #[serde(default)]
+    pub default: DatabaseWebsiteGeoAction,
+    /// 通过验证后 cookie 的有效期，单位为秒
+    #[serde(default = "default_geo_challenge_ttl")]
+    pub challenge_ttl: u64,
+}
+
+impl Default for DatabaseWebsiteGeo {
+    fn default() -> Self {
+        Self {
+            rules: vec![],
+            default: DatabaseWebsiteGeoAction::default(),
+            challenge_ttl: default_geo_challenge_ttl(),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct DatabaseWebsiteGeoRule {
+    /// 国家代码，如 CN、US
+    #[serde(default)]
+    pub countries: Vec<String>,
+    /// 国家代码加地区代码，如 CN-GD
+    #[serde(default)]
+    pub regions: Vec<String>,
+    pub action: DatabaseWebsiteGeoAction,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize, Default)]
+#[serde(rename_all = "camelCase")]
+pub enum DatabaseWebsiteGeoAction {
+    #[default]
+    Allow,
+    Deny,
+    /// 返回 JS 工作量证明页面，提交有效的签名 cookie 后才转发
+    Challenge,
+}
+
+/// HTTP Basic 认证，users 为空时不启用
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct DatabaseWebsiteBasicAuth {
+    #[serde(default = "default_basic_auth_realm")]
+    pub realm: String,
+    #[serde(default)]
+    pub users: Vec<DatabaseWebsiteBasicAuthUser>,
+    /// 以这些前缀开头的路径不需要认证
+    #[serde(default)]
+    pub exclude_paths: Vec<String>,
+}
+
+impl Default for DatabaseWebsiteBasicAuth {
+    fn default() -> Self {
+        Self {
+            realm: default_basic_auth_realm(),
+            users: vec![],
+            exclude_paths: vec![],
+        }
+    }
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct DatabaseWebsiteBasicAuthUser {
+    pub username: String,
+    /// argon2（$argon2id$...）或 bcrypt（$2b$...）格式的哈希
+    pub password_hash: String,
+}
+
+/// 转发前先请求认证服务，返回 2xx 才转发，3xx / 401 / 403 原样返回给客户端
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct DatabaseWebsiteForwardAuth {
+    pub url: Url,
+    /// 复制到认证请求的客户端头部
+    #[serde(default = "default_forward_auth_request_headers")]
+    pub request_headers: Vec<String>,
+    /// 认证通过后从认证响应复制到上游请求的头部，客户端发送的同名头部会被删除
+    #[serde(default)]
+    pub response_headers: Vec<String>,
+    /// 单位为秒
+    #[serde(default = "default_forward_auth_timeout")]
+    pub timeout: u64,
+    /// 仅在 https:// 地址生效
+    #[serde(default)]
+    pub tls: DatabaseWebsiteBackendTls,
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize, Default)]
@@ -71,4 +752,46 @@ pub struct CreateDatabaseWebsite {
     pub certificates: Vec<ObjectId>,
     pub backends: Vec<DatabaseWebsiteBackend>,
     pub config: Option<DatabaseWebsiteConfig>,
+    #[serde(default)]
+    pub routes: Vec<DatabaseWebsiteRoute>,
+    #[serde(default)]
+    pub geo_backends: Vec<DatabaseWebsiteGeoBackends>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct DatabaseWebsiteBackendHealth {
+    pub website_id: ObjectId,
+    /// 路由序号，网站自身的 backends 为 default
+    pub route: String,
+    pub url: Url,
+    pub main: bool,
+    pub healthy: bool,
+    pub status: Option<u16>,
+    pub error: Option<String>,
+    pub checked_at: DateTime<Utc>,
+    pub changed_at: DateTime<Utc>,
+}
+
+impl<'r> FromRow<'r, PgRow> for DatabaseWebsiteBackendHealth {
+    fn from_row(row: &PgRow) -> Result<Self, Error> {
+        Ok(DatabaseWebsiteBackendHealth {
+            website_id: row.try_get("website_id")?,
+            route: row.try_get("route")?,
+            url: row
+                .try_get::<String, _>("url")?
+                .parse()
+                .map_err(|e| Error::Decode(Box::new(e)))?,
+            main: row.try_get("main")?,
+            healthy: row.try_get("healthy")?,
+            status: row.try_get::<Option<U16>, _>("status")?.map(u16::from),
+            error: row.try_get("error")?,
+            checked_at: row.try_get("checked_at")?,
+            changed_at: row.try_get("changed_at")?,
+        })
+    }
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct WebsiteHealthQueryParams {
+    pub id: Option<ObjectId>,
 }
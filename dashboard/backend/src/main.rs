@@ -17,7 +17,6 @@ pub mod certificate;
 pub mod config;
 mod database;
 mod foundation;
-pub mod ip;
 pub mod mnt;
 pub mod models;
 pub mod response;
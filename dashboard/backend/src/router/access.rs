@@ -3,10 +3,10 @@ use std::collections::HashMap;
 use axum::{Router, extract::Query, middleware, routing::get};
 use shared::{
     database::{access::DatabaseAccessLogsRepository, get_database},
-    models::access::{AccessInfo, QueryAccessInfo, QueryAccessMap, QueryQPS, QueryQPSType, ResponseQPS, TodayMetricsInfoOfWebsite},
+    models::access::{AccessInfo, QueryAccessInfo, QueryAccessMap, QueryAccessMapType, QueryQPS, QueryQPSType, ResponseQPS, TodayMetricsInfoOfWebsite},
 };
 
-use crate::{auth::middle_refresh_token, ip, response::APIResponse};
+use crate::{auth::middle_refresh_token, response::APIResponse};
 
 pub async fn qps(Query(query): Query<QueryQPS>) -> APIResponse<ResponseQPS> {
     APIResponse::result(match query.interval {
@@ -25,63 +25,11 @@ pub async fn website_metrics_info(
 }
 
 pub async fn access_map(Query(query): Query<QueryAccessMap>) -> APIResponse<HashMap<String, usize>> {
-    #[cfg(not(debug_assertions))]
-    {
-        return APIResponse::ok(HashMap::new());
-    }
-    let res = match get_database().get_requests_of_ips(query.in_days.into()).await {
-        Ok(res) => res,
-        Err(e) => {
-            return APIResponse::error(None, 500, e.to_string());
-        }
-    };
-
-    let mut result: HashMap<String, usize> = HashMap::new();
-    for (ip, count) in res {
-        match ip.parse() {
-            Ok(ip) => {
-                let info = match ip::lookup(ip) {
-                    Ok(info) => info,
-                    Err(_) => {
-                        continue;
-                    }
-                };
-                // println!("{}: {:?}", ip, info);
-                match query.map_type {
-                    shared::models::access::QueryAccessMapType::Global => {
-                        match info.country {
-                            Some(country) => {
-                                *result.entry(country).or_insert(0) += count;
-                            },
-                            None => {
-                                *result.entry("Unknown".to_string()).or_insert(0) += count;
-                            }
-                        }
-                    },
-                    shared::models::access::QueryAccessMapType::China => {
-                        if let Some(country) = info.country && country == "CN" {
-                        match info.city {
-                            Some(city) => {
-                                *result.entry(city).or_insert(0) += count;
-                            },
-                            None => {
-                                *result.entry("Unknown".to_string()).or_insert(0) += count;
-                            }
-                        }
-                        }
-                    }
-                }
-                
-            },
-            Err(_) => {
-                continue;
-            }
-        }
-    }
-
-    APIResponse::ok(result)
-    
-
+    // 国家和地区在网关写入访问日志时已经解析
+    APIResponse::result(match query.map_type {
+        QueryAccessMapType::Global => get_database().get_requests_of_countries(query.in_days.into()).await,
+        QueryAccessMapType::China => get_database().get_requests_of_regions(query.in_days.into(), "CN").await,
+    })
 }
 
 pub fn router() -> Router {
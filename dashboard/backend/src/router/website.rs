@@ -1,5 +1,7 @@
 use axum::{
-    Json, Router, middleware,
+    Json, Router,
+    extract::Query,
+    middleware,
     routing::{get, post},
 };
 use shared::{
@@ -7,7 +9,10 @@ use shared::{
         get_database,
         websites::{DatabaseWebsiteModifyRepository, DatabaseWebsiteRepository},
     },
-    models::websites::{CreateDatabaseWebsite, DatabaseWebsite},
+    models::websites::{
+        CreateDatabaseWebsite, DatabaseWebsite, DatabaseWebsiteBackendHealth,
+        WebsiteHealthQueryParams,
+    },
 };
 
 use crate::{auth::middle_refresh_token, response::APIResponse};
@@ -20,9 +25,20 @@ pub async fn create(Json(data): Json<CreateDatabaseWebsite>) -> APIResponse<Data
     APIResponse::result(get_database().create_website(&data).await)
 }
 
+pub async fn health(
+    Query(query): Query<WebsiteHealthQueryParams>,
+) -> APIResponse<Vec<DatabaseWebsiteBackendHealth>> {
+    APIResponse::result(
+        get_database()
+            .get_website_backends_health(query.id.as_ref())
+            .await,
+    )
+}
+
 pub fn router() -> Router {
     Router::new()
         .route("/", get(get_all))
+        .route("/health", get(health))
         .route("/create", post(create))
         .layer(middleware::from_fn(middle_refresh_token))
 }
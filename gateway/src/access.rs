@@ -2,13 +2,15 @@ use std::sync::{Arc, LazyLock, RwLock};
 
 use chrono::{DateTime, TimeDelta, Timelike, Utc};
 use dashmap::DashMap;
+use geoip::LookupResult;
 use http_body::SizeHint;
 use hyper::{HeaderMap, Uri, Version};
 use shared::{
     database::{access::DatabaseAccessLogsModifyRepository, get_database},
     models::access::{
-        AccessCreateRequest, AccessCreateResponse, AccessInsertRequestSize,
-        AccessInsertResponseSize, AccessUpdateRequestSize, AccessUpdateResponseSize, AccessVersion,
+        AccessAuthStatus, AccessCacheStatus, AccessCreateAttempt, AccessCreateRequest,
+        AccessCreateResponse, AccessInsertRequestSize, AccessInsertResponseSize,
+        AccessUpdateRequestSize, AccessUpdateResponseSize, AccessVersion,
     },
     objectid::ObjectId,
 };
@@ -30,6 +32,7 @@ pub struct RequestContext {
     pub body_length: SizeHint,
     pub remote_addr: String,
     pub website_id: Option<ObjectId>,
+    pub location: Option<LookupResult>,
 }
 
 impl RequestLog {
@@ -65,6 +68,8 @@ impl RequestLog {
                 body_length: context.body_length.lower().try_into().unwrap(),
                 requested_at: get_database().get_database_time().unwrap(),
                 website_id: context.website_id,
+                country: context.location.as_ref().and_then(|v| v.country.clone()),
+                region: context.location.and_then(|v| v.city),
             },
         })
     }
@@ -110,15 +115,33 @@ impl ResponseLog {
                 responsed_at: get_database().get_database_time().unwrap(),
                 backend_responsed_at,
                 website_id,
+                cache_status: None,
+                auth_status: None,
+                auth_user: None,
             },
         })
     }
+
+    /// 启用了缓存的路由记录缓存的处理结果
+    pub fn with_cache_status(mut self, cache_status: Option<AccessCacheStatus>) -> Self {
+        self.inner.cache_status = cache_status;
+        self
+    }
+
+    /// 启用了 Basic 认证的路由记录认证结果和用户名
+    pub fn with_auth(mut self, status: AccessAuthStatus, user: Option<String>) -> Self {
+        self.inner.auth_status = Some(status);
+        self.inner.auth_user = user;
+        self
+    }
 }
 
 static ACCESS_REQUEST_LOGS: LazyLock<DashMap<Arc<DateTime<Utc>>, Vec<AccessCreateRequest>>> =
     LazyLock::new(DashMap::new);
 static ACCESS_RESPONSE_LOGS: LazyLock<DashMap<Arc<DateTime<Utc>>, Vec<AccessCreateResponse>>> =
     LazyLock::new(DashMap::new);
+static ACCESS_ATTEMPT_LOGS: LazyLock<DashMap<Arc<DateTime<Utc>>, Vec<AccessCreateAttempt>>> =
+    LazyLock::new(DashMap::new);
 static ACCESS_REQUEST_SIZE_LOGS: LazyLock<DashMap<ObjectId, usize>> = LazyLock::new(DashMap::new);
 static ACCESS_RESPONSE_SIZE_LOGS: LazyLock<DashMap<ObjectId, usize>> = LazyLock::new(DashMap::new);
 
@@ -181,6 +204,12 @@ async fn sync() {
             event!(Level::ERROR, "Failed to sync access response logs: {}", e);
         }
     }
+    match sync_access_attempt_logs().await {
+        Ok(_) => {}
+        Err(e) => {
+            event!(Level::ERROR, "Failed to sync access attempt logs: {}", e);
+        }
+    }
     let sync_request_size_logs_thread = tokio::spawn(async move {
         match sync_request_size_logs().await {
             Ok(_) => {}
@@ -295,6 +324,27 @@ async fn sync_access_response_logs() -> anyhow::Result<()> {
     Ok(())
 }
 
+async fn sync_access_attempt_logs() -> anyhow::Result<()> {
+    let logs = ACCESS_ATTEMPT_LOGS.clone();
+    let current_time = { CURRENT_TIME.read().unwrap().clone() };
+    // fetch before current_time
+    let logs = logs
+        .iter()
+        .filter_map(|v| match v.key() < &current_time {
+            true => Some(v.value().clone()),
+            false => None,
+        })
+        .flatten()
+        .collect::<Vec<_>>();
+    if logs.is_empty() {
+        return Ok(());
+    }
+    // first clean old
+    ACCESS_ATTEMPT_LOGS.retain(|k, _| k > &current_time);
+    get_database().insert_batch_access_attempts(logs).await?;
+    Ok(())
+}
+
 async fn sync_request_size_logs() -> anyhow::Result<()> {
     // clone and delete
     let logs = ACCESS_REQUEST_SIZE_LOGS.clone();
@@ -391,6 +441,12 @@ pub fn add_response_log(log: &ResponseLog) {
     logs.push(log.inner.clone());
 }
 
+pub fn add_attempt_logs(attempts: Vec<AccessCreateAttempt>) {
+    let current_time = { CURRENT_TIME.read().unwrap().clone() };
+    let mut logs = ACCESS_ATTEMPT_LOGS.entry(current_time).or_default();
+    logs.extend(attempts);
+}
+
 pub fn update_request_size_log(id: ObjectId, size: usize) {
     ACCESS_REQUEST_SIZE_LOGS.insert(id, size);
     // .
@@ -1,4 +1,8 @@
-use std::sync::OnceLock;
+use std::{
+    collections::HashMap,
+    net::{IpAddr, SocketAddr},
+    sync::OnceLock,
+};
 
 use serde::{Deserialize, Serialize};
 use shared::default::default_database_max_connections;
@@ -13,6 +17,42 @@ pub struct MainConfig {
         rename = "database_max_connections"
     )]
     pub max_connections: u32,
+    #[serde(default)]
+    pub dns: DnsConfig,
+}
+
+/// 后端域名解析，min_ttl / max_ttl 单位为秒
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct DnsConfig {
+    /// 为空时使用系统配置（/etc/resolv.conf）
+    #[serde(default)]
+    pub nameservers: Vec<SocketAddr>,
+    /// 静态解析，优先于 DNS 查询
+    #[serde(default)]
+    pub hosts: HashMap<String, Vec<IpAddr>>,
+    #[serde(default = "config_dns_min_ttl")]
+    pub min_ttl: u64,
+    #[serde(default = "config_dns_max_ttl")]
+    pub max_ttl: u64,
+}
+
+impl Default for DnsConfig {
+    fn default() -> Self {
+        Self {
+            nameservers: vec![],
+            hosts: HashMap::new(),
+            min_ttl: config_dns_min_ttl(),
+            max_ttl: config_dns_max_ttl(),
+        }
+    }
+}
+
+fn config_dns_min_ttl() -> u64 {
+    5
+}
+
+fn config_dns_max_ttl() -> u64 {
+    300
 }
 
 fn config_max_connections() -> u32 {
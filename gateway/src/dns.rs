@@ -1 +1,145 @@
+use std::{
+    net::{IpAddr, SocketAddr},
+    sync::{LazyLock, Weak},
+    time::Duration,
+};
 
+use dashmap::DashMap;
+use hickory_resolver::{
+    TokioResolver,
+    config::{NameServerConfig, ResolverConfig},
+    name_server::TokioConnectionProvider,
+    proto::xfer::Protocol,
+};
+use tokio::time::Instant;
+use tracing::{Level, event};
+
+use crate::{config::get_config, proxy::backends::BackendConnectionPool};
+
+static RESOLVER: LazyLock<TokioResolver> = LazyLock::new(|| {
+    let nameservers = &get_config().dns.nameservers;
+    if nameservers.is_empty() {
+        match TokioResolver::builder_tokio() {
+            Ok(builder) => return builder.build(),
+            Err(e) => {
+                event!(
+                    Level::WARN,
+                    "Failed to load system DNS config, use default nameservers: {e}"
+                );
+            }
+        }
+    }
+    let config = match nameservers.is_empty() {
+        true => ResolverConfig::default(),
+        false => ResolverConfig::from_parts(
+            None,
+            vec![],
+            nameservers
+                .iter()
+                .flat_map(|addr| {
+                    [
+                        NameServerConfig::new(*addr, Protocol::Udp),
+                        NameServerConfig::new(*addr, Protocol::Tcp),
+                    ]
+                })
+                .collect::<Vec<_>>(),
+        ),
+    };
+    TokioResolver::builder_with_config(config, TokioConnectionProvider::default()).build()
+});
+
+static CACHE: LazyLock<DashMap<String, Resolved>> = LazyLock::new(DashMap::new);
+
+#[derive(Debug, Clone)]
+pub struct Resolved {
+    pub addrs: Vec<IpAddr>,
+    /// IP 或静态解析时为 None，不需要重新解析
+    pub valid_until: Option<Instant>,
+}
+
+impl Resolved {
+    pub fn socket_addrs(&self, port: u16) -> Vec<SocketAddr> {
+        self.addrs
+            .iter()
+            .map(|ip| SocketAddr::new(*ip, port))
+            .collect()
+    }
+}
+
+/// 解析主机名，结果按记录 TTL 缓存（限制在 min_ttl 与 max_ttl 之间）
+pub async fn resolve(host: &str) -> anyhow::Result<Resolved> {
+    let host = host.trim_start_matches('[').trim_end_matches(']');
+    if let Ok(ip) = host.parse::<IpAddr>() {
+        return Ok(Resolved {
+            addrs: vec![ip],
+            valid_until: None,
+        });
+    }
+    let host = host.to_ascii_lowercase();
+    let config = &get_config().dns;
+    if let Some(addrs) = config.hosts.get(&host) {
+        return Ok(Resolved {
+            addrs: addrs.clone(),
+            valid_until: None,
+        });
+    }
+    if let Some(cached) = CACHE.get(&host)
+        && cached.valid_until.is_some_and(|v| v > Instant::now())
+    {
+        return Ok(cached.clone());
+    }
+    let lookup = RESOLVER.lookup_ip(host.as_str()).await?;
+    let addrs = lookup.iter().collect::<Vec<IpAddr>>();
+    if addrs.is_empty() {
+        return Err(anyhow::anyhow!("No found any address of {host}"));
+    }
+    let now = Instant::now();
+    let ttl = Instant::from_std(lookup.valid_until())
+        .saturating_duration_since(now)
+        .clamp(
+            Duration::from_secs(config.min_ttl),
+            Duration::from_secs(config.max_ttl.max(config.min_ttl)),
+        );
+    let resolved = Resolved {
+        addrs,
+        valid_until: Some(now + ttl),
+    };
+    CACHE.insert(host, resolved.clone());
+    Ok(resolved)
+}
+
+/// TTL 到期后在后台重新解析，地址变化时替换连接池的目标，连接池释放后退出
+pub fn spawn_refresh(
+    host: String,
+    port: u16,
+    pool: Weak<BackendConnectionPool>,
+    valid_until: Instant,
+) {
+    tokio::spawn(async move {
+        let mut valid_until = valid_until;
+        loop {
+            tokio::time::sleep_until(valid_until).await;
+            let Some(pool) = pool.upgrade() else {
+                break;
+            };
+            match resolve(&host).await {
+                Ok(resolved) => {
+                    let Some(next) = resolved.valid_until else {
+                        break;
+                    };
+                    valid_until = next;
+                    let addrs = resolved.socket_addrs(port);
+                    if pool.set_targets(addrs.clone()) {
+                        event!(Level::INFO, "Backend {host} resolved to {addrs:?}");
+                    }
+                }
+                Err(e) => {
+                    // 解析失败时保留旧地址，稍后重试
+                    event!(Level::WARN, "Failed to resolve backend {host}: {e}");
+                    valid_until =
+                        Instant::now() + Duration::from_secs(get_config().dns.min_ttl.max(1));
+                }
+            }
+        }
+    });
+}
@@ -6,7 +6,14 @@ use std::{
 
 use dashmap::DashMap;
 use http_body::Body;
-use hyper::{Request, Response, StatusCode, Version, body::Incoming, client, service::service_fn};
+use http_body_util::BodyExt;
+use hyper::{
+    Request, Response, StatusCode,
+    body::Incoming,
+    header::{AUTHORIZATION, HeaderValue, RETRY_AFTER},
+    http::request::{self, Parts},
+    service::service_fn,
+};
 use hyper_util::{
     rt::{TokioExecutor, TokioIo},
     server::conn::auto::Builder,
@@ -14,21 +21,52 @@ use hyper_util::{
 use shared::{
     database::get_database,
     listener::CustomDualStackTcpListener,
+    models::access::{AccessAuthStatus, AccessCacheStatus, AccessCreateAttempt},
     objectid::ObjectId,
     streams::{BufferStream, WrapperBufferStream},
 };
-use tokio::{net::TcpStream, task::JoinHandle, time::timeout};
+use tokio::{
+    net::TcpStream,
+    task::JoinHandle,
+    time::{Instant, error::Elapsed, timeout, timeout_at},
+};
 use tokio_rustls::TlsAcceptor;
 use tracing::{Level, event};
 
 use crate::{
     access::{self, RequestContext, RequestLog, ResponseLog},
+    proxy::{
+        basicauth::BasicAuthResult,
+        forwardauth::ForwardAuthResult,
+        group::WebSiteBackend,
+        retry::RetryBody,
+        target::{InvalidTarget, build_target},
+    },
     state::{BaseClientState, ClientState},
-    sync::{SERVER_CONFIG, websites::get_website},
+    sync::{SERVER_CONFIG, ipaccess::is_ip_allowed, websites::get_website},
     transport::{CResponse, CResponseResult, StatisticsIncoming},
 };
 pub mod backends;
+pub mod basicauth;
+pub mod cache;
+pub mod compression;
+pub mod error_page;
+pub mod files;
+pub mod forwardauth;
+pub mod geo;
+pub mod group;
+pub mod headers;
+pub mod health;
+pub mod outlier;
 pub mod protocols;
+pub mod ratelimit;
+pub mod redirect;
+pub mod retry;
+pub mod rewrite;
+pub mod route;
+pub mod target;
+pub mod tls;
+pub mod upgrade;
 
 static HTTP_BUILDER: LazyLock<Builder<TokioExecutor>> = LazyLock::new(|| {
     hyper_util::server::conn::auto::Builder::<TokioExecutor>::new(TokioExecutor::new())
@@ -78,22 +116,27 @@ async fn handle_connection(stream: TcpStream, addr: SocketAddr) -> anyhow::Resul
     let (stream, _) = protocols::get_proxy_protocol(stream).await?;
     // event!(Level::INFO, "Proxy protocol: {:?}", proxy_protocol);
     let (stream, tls) = protocols::get_tls_sni(stream).await?;
-    let final_stream = match &tls {
+    let (final_stream, tls_version) = match &tls {
         Some(_) => {
             let s = TLS_ACCEPTOR.accept(stream).await?;
-            BufferStream::new(WrapperBufferStream::TlsServerBufferStream(Box::new(s)))
+            let version = s.get_ref().1.protocol_version();
+            (
+                BufferStream::new(WrapperBufferStream::TlsServerBufferStream(Box::new(s))),
+                version,
+            )
         }
-        None => stream,
+        None => (stream, None),
     };
     // if is proxyprotocol
-    let state = Arc::new(BaseClientState {
+    let state = Arc::new(BaseClientState::new(
         tls,
-        remote_addr: addr.ip(),
-        local_addr: local_addr.ip(),
-    });
+        tls_version,
+        addr.ip(),
+        local_addr.ip(),
+    ));
     let io = TokioIo::new(final_stream);
     let _ = HTTP_BUILDER
-        .serve_connection(
+        .serve_connection_with_upgrades(
             io,
             service_fn(move |req: Request<Incoming>| {
                 let state = state.clone();
@@ -127,6 +170,7 @@ pub async fn handle(
 ) -> anyhow::Result<hyper::Response<CResponse>> {
     let site = get_website(&host).await;
     let website_id = site.as_ref().map(|v| v.inner().id);
+    let json = error_page::prefers_json(req.headers());
     let req_log = RequestLog::new(RequestContext {
         req_id,
         host: host.clone(),
@@ -137,34 +181,48 @@ pub async fn handle(
         body_length: req.body().size_hint(),
         remote_addr: base_state.remote_addr.to_string(),
         website_id,
+        location: base_state.location().cloned(),
     });
     let resp = match req_log {
+        Ok(req_log) if !is_ip_allowed(website_id.as_ref(), base_state.remote_addr) => {
+            access::add_request_log(&req_log);
+            CResponseResult::Forbidden
+        }
         Ok(req_log) => {
             access::add_request_log(&req_log);
-            let site = get_website(&host).await;
+            let site = get_website(&host).await.and_then(|site| {
+                site.route(req.method(), req.uri().path(), req.headers())
+                    .map(|route| (site, route))
+            });
             match site {
-                Some(site) => {
+                Some((site, route)) => {
                     let state = ClientState {
                         base: base_state,
-                        website: site.clone(),
+                        website: site,
+                        route,
                         host,
                         id: req_id,
                     };
                     let resp = wrapper_inner_handle(req, state).await;
                     match resp {
                         CResponseResult::Backend(resp) => {
-                            access::add_response_log(
-                                &ResponseLog::new(
-                                    req_id,
-                                    resp.version(),
-                                    resp.headers(),
-                                    resp.status().as_u16(),
-                                    resp.body().size_hint(),
-                                    Some(get_database().get_database_time().unwrap()),
-                                    website_id,
-                                )
-                                .unwrap(),
+                            let mut resp_log = ResponseLog::new(
+                                req_id,
+                                resp.version(),
+                                resp.headers(),
+                                resp.status().as_u16(),
+                                resp.body().size_hint(),
+                                Some(get_database().get_database_time().unwrap()),
+                                website_id,
+                            )
+                            .unwrap()
+                            .with_cache_status(
+                                resp.extensions().get::<AccessCacheStatus>().copied(),
                             );
+                            if let Some(auth) = resp.extensions().get::<BasicAuthResult>() {
+                                resp_log = resp_log.with_auth(auth.status, auth.user.clone());
+                            }
+                            access::add_response_log(&resp_log);
                             return Ok(resp);
                         }
                         resp => resp,
@@ -178,25 +236,23 @@ pub async fn handle(
 
     // let resp = wrapper_inner_handle(req, base_state, host, &req_id).await;
 
-    let final_resp = match resp {
-        CResponseResult::NotFoundGateway => Response::builder()
-            .status(StatusCode::NOT_FOUND)
-            .body(CResponse::new_from_string("Not Found"))
-            .unwrap(),
-        CResponseResult::GatewayError(e) => Response::builder()
-            .status(StatusCode::INTERNAL_SERVER_ERROR)
-            .body(CResponse::new_from_string(e.to_string()))
-            .unwrap(),
-        CResponseResult::Timeout => Response::builder()
-            .status(StatusCode::REQUEST_TIMEOUT)
-            .body(CResponse::new_from_string("Request Timeout"))
-            .unwrap(),
-        CResponseResult::BadRequest => Response::builder()
-            .status(StatusCode::BAD_REQUEST)
-            .body(CResponse::new_from_string("Bad Request"))
-            .unwrap(),
+    let status = match resp {
+        CResponseResult::NotFoundGateway => StatusCode::NOT_FOUND,
+        CResponseResult::GatewayError(e) => {
+            event!(Level::WARN, "Request {req_id} failed: {e:?}");
+            StatusCode::INTERNAL_SERVER_ERROR
+        }
+        CResponseResult::Timeout => StatusCode::GATEWAY_TIMEOUT,
+        CResponseResult::BadRequest => StatusCode::BAD_REQUEST,
+        CResponseResult::Forbidden => StatusCode::FORBIDDEN,
         CResponseResult::Backend(_) => unreachable!(),
     };
+    let final_resp = error_page::error_response(
+        status,
+        &req_id,
+        json,
+        site.as_ref().map(|v| &v.inner().config.error_pages),
+    );
     access::add_response_log(
         &ResponseLog::new(
             req_id,
@@ -216,10 +272,17 @@ async fn wrapper_inner_handle(
     req: Request<StatisticsIncoming>,
     state: ClientState,
 ) -> CResponseResult {
-    let resp = timeout(Duration::from_secs(60), inner_handle(req, state)).await;
+    let config = &state.route.config().timeout;
+    let deadline = (config.total > 0).then(|| Instant::now() + Duration::from_secs(config.total));
+    let resp = match deadline {
+        Some(deadline) => timeout_at(deadline, inner_handle(req, state, deadline.into())).await,
+        None => Ok(inner_handle(req, state, None).await),
+    };
     match resp {
         Ok(v) => match v {
             Ok(v) => CResponseResult::Backend(v),
+            Err(e) if e.is::<Elapsed>() => CResponseResult::Timeout,
+            Err(e) if e.is::<InvalidTarget>() => CResponseResult::BadRequest,
             Err(e) => CResponseResult::GatewayError(e),
         },
         Err(_) => CResponseResult::Timeout,
@@ -227,41 +290,372 @@ async fn wrapper_inner_handle(
 }
 
 async fn inner_handle(
-    origin_req: Request<StatisticsIncoming>,
+    mut origin_req: Request<StatisticsIncoming>,
     state: ClientState,
+    deadline: Option<Instant>,
 ) -> anyhow::Result<hyper::Response<CResponse>> {
-    let site = &state.website;
+    let route = &state.route;
+
+    if let Some(resp) = state.website.geo().check(&state, origin_req.headers()) {
+        return Ok(resp);
+    }
+
+    if let Some(wait) =
+        route
+            .rate_limiter()
+            .check(&state, origin_req.uri().path(), origin_req.headers())
+    {
+        let mut resp = error_page::error_response(
+            StatusCode::TOO_MANY_REQUESTS,
+            &state.id,
+            error_page::prefers_json(origin_req.headers()),
+            Some(&state.website.inner().config.error_pages),
+        );
+        resp.headers_mut().insert(
+            RETRY_AFTER,
+            HeaderValue::from(wait.as_secs_f64().ceil().max(1.0) as u64),
+        );
+        return Ok(resp);
+    }
 
-    let pool = site.pool();
-    let conn = pool.get().await?;
-    let io = TokioIo::new(conn);
-    let (mut c_req, connection) = client::conn::http1::handshake(io).await?;
-    tokio::task::spawn(async move {
-        if let Err(err) = connection.await {
-            eprintln!("Connection error: {}", err);
+    // 重定向（包括强制 HTTPS）需要在认证之前，避免凭据经明文发送
+    if let Some(mut resp) = route.redirects().redirect(&state, origin_req.uri())? {
+        route.headers().apply_response(resp.headers_mut(), &state)?;
+        return Ok(resp);
+    }
+
+    let auth = match route.basic_auth() {
+        Some(basic_auth) => match basic_auth
+            .authenticate(origin_req.uri().path(), origin_req.headers())
+            .await
+        {
+            Some(result) if result.status != AccessAuthStatus::Success => {
+                return Ok(basic_auth.unauthorized(&state, origin_req.headers(), result));
+            }
+            result => result,
+        },
+        None => None,
+    };
+    if auth.is_some() {
+        // 凭据只用于网关认证，不转发给后端
+        origin_req.headers_mut().remove(AUTHORIZATION);
+    }
+
+    if let Some(forward_auth) = route.forward_auth() {
+        match forward_auth
+            .check(
+                &state,
+                origin_req.method(),
+                origin_req.uri(),
+                origin_req.headers(),
+                deadline,
+            )
+            .await?
+        {
+            ForwardAuthResult::Allow(granted) => {
+                forward_auth.apply(origin_req.headers_mut(), granted)
+            }
+            ForwardAuthResult::Deny(resp) => return Ok(resp),
         }
-    });
+    }
+
+    // 缓存键不区分用户，认证后的响应不能缓存
+    let bypass_cache = auth.is_some() || route.forward_auth().is_some();
+    let mut resp = forward(origin_req, state, deadline, bypass_cache).await?;
+    if let Some(auth) = auth {
+        resp.extensions_mut().insert(auth);
+    }
+    Ok(resp)
+}
+
+/// 通过检查后按路由处理：静态文件或转发到后端
+async fn forward(
+    mut origin_req: Request<StatisticsIncoming>,
+    state: ClientState,
+    deadline: Option<Instant>,
+    bypass_cache: bool,
+) -> anyhow::Result<hyper::Response<CResponse>> {
+    let route = &state.route;
+    let config = route.config();
+    let timeouts = &config.timeout;
+    let retry = &config.retry;
+    let idle = (timeouts.idle > 0).then(|| Duration::from_secs(timeouts.idle));
+
+    if let Some(files) = route.files() {
+        let path = match route.rewrite() {
+            Some(rewrite) => rewrite.rewrite(origin_req.uri().path(), None).0,
+            None => origin_req.uri().path().to_string(),
+        };
+        let mut resp = match files
+            .serve(
+                origin_req.method(),
+                &path,
+                origin_req.uri(),
+                origin_req.headers(),
+            )
+            .await?
+        {
+            Ok(resp) => resp,
+            Err(status) => error_page::error_response(
+                status,
+                &state.id,
+                error_page::prefers_json(origin_req.headers()),
+                Some(&state.website.inner().config.error_pages),
+            ),
+        };
+        route.headers().apply_response(resp.headers_mut(), &state)?;
+        return Ok(route
+            .compression()
+            .apply(origin_req.method(), origin_req.headers(), resp));
+    }
+    if route.group().is_none() {
+        return Err(anyhow::anyhow!("No found any backends"));
+    }
+
     let origin_version = origin_req.version();
-    let mut req = Request::builder()
-        .method(origin_req.method())
-        .version(Version::HTTP_11);
+    // 需要在复制 extensions 之前取出客户端的升级句柄
+    let client_upgrade = upgrade::is_upgrade_request(origin_req.headers())
+        .then(|| hyper::upgrade::on(&mut origin_req));
+    let idempotent = retry::is_idempotent(origin_req.method());
+    let (parts, body) = origin_req.into_parts();
+    let body = body.with_timeout(deadline, idle);
+    let cache = route.cache().filter(|v| {
+        !bypass_cache
+            && client_upgrade.is_none()
+            && body.size_hint().exact() == Some(0)
+            && v.is_cacheable_request(&parts)
+    });
+    let mut final_resp = match cache {
+        Some(cache) => cache.handle(&state, &parts, deadline, idle).await?,
+        None => {
+            // 允许重试的幂等请求，body 不大时先缓存以便重放
+            let mut body = match retry.max_attempts > 1
+                && idempotent
+                && client_upgrade.is_none()
+                && body
+                    .size_hint()
+                    .upper()
+                    .is_some_and(|v| v <= retry.max_replay_body as u64)
+            {
+                true => RetryBody::Replay(
+                    body.collect()
+                        .await
+                        .map_err(|e| anyhow::anyhow!(e))?
+                        .to_bytes(),
+                ),
+                false => RetryBody::Stream(Some(body.boxed_unsync())),
+            };
+            let mut resp = send_to_backend(
+                &state,
+                &parts,
+                &mut body,
+                idempotent,
+                client_upgrade.is_some(),
+            )
+            .await?;
+            if let Some(client_upgrade) = client_upgrade
+                && resp.status() == StatusCode::SWITCHING_PROTOCOLS
+            {
+                tokio::spawn(upgrade::tunnel(
+                    state.id,
+                    client_upgrade,
+                    hyper::upgrade::on(&mut resp),
+                    Duration::from_secs(config.upgrade_idle_timeout.max(1)),
+                ));
+            }
+            wrap_incoming(&state, resp, deadline, idle)
+        }
+    };
+    route
+        .headers()
+        .apply_response(final_resp.headers_mut(), &state)?;
+    *final_resp.version_mut() = origin_version;
+    Ok(route
+        .compression()
+        .apply(&parts.method, &parts.headers, final_resp))
+}
+
+/// 按重试配置选择后端发送请求，记录每次尝试
+async fn send_to_backend(
+    state: &ClientState,
+    parts: &Parts,
+    body: &mut RetryBody,
+    idempotent: bool,
+    upgrade: bool,
+) -> anyhow::Result<Response<Incoming>> {
+    let route = &state.route;
+    let config = route.config();
+    let retry = &config.retry;
+    let try_timeout = Duration::from_secs(match retry.per_try_timeout {
+        0 => config.timeout.header.max(1),
+        v => v,
+    });
+    let (_, group) = route
+        .select_group(state.base.location())
+        .ok_or(anyhow::anyhow!("No found any backends"))?;
+    route.retry_budget().record_request();
+
+    let mut tried: Vec<Arc<WebSiteBackend>> = vec![];
+    let mut attempts = vec![];
+    let result = loop {
+        let attempt = attempts.len() + 1;
+        let can_retry = attempt < retry.max_attempts;
+        // 重试时优先换一个没试过的后端
+        let backend = match tried.is_empty() {
+            true => None,
+            false => group.select_by(|b| {
+                b.is_available() && !tried.iter().any(|t| std::ptr::eq(t.as_ref(), b))
+            }),
+        }
+        .or_else(|| group.select())
+        .ok_or(anyhow::anyhow!("No available backends"))?;
+        tried.push(backend.clone());
+        let started = Instant::now();
+        let pool = backend.pool();
+
+        let mut sender = match pool.get().await {
+            Ok(v) => v,
+            Err(e) => {
+                attempts.push(attempt_log(
+                    state,
+                    attempt,
+                    &backend,
+                    None,
+                    Some(&e),
+                    started,
+                ));
+                // 连接失败时请求还没有发出，body 仍可使用
+                if can_retry && retry.connect_failure && route.retry_budget().try_retry() {
+                    event!(
+                        Level::WARN,
+                        "Retry request {} after connect failure: {e}",
+                        state.id
+                    );
+                    continue;
+                }
+                break Err(e);
+            }
+        };
+        if upgrade && sender.is_multiplexed() {
+            break Err(anyhow::anyhow!(
+                "Upgrade is not supported by HTTP/2 backends"
+            ));
+        }
+        let req = build_backend_request(parts, state, &backend)?.body(body.take()?)?;
+        let replayable = idempotent && body.is_replayable();
+
+        // 未上报结果（出错或超时被取消）时计为一次失败
+        let outcome = pool.outcome(sender.peer_addr());
+        let result = match timeout(try_timeout, sender.send_request(req)).await {
+            Ok(v) => v,
+            Err(e) => Err(e.into()),
+        };
+        match result {
+            Ok(resp) => {
+                let status = resp.status().as_u16();
+                if resp.status().is_server_error() {
+                    outcome.failure();
+                } else {
+                    outcome.success();
+                }
+                attempts.push(attempt_log(
+                    state,
+                    attempt,
+                    &backend,
+                    Some(status),
+                    None,
+                    started,
+                ));
+                if can_retry
+                    && replayable
+                    && retry.status.contains(&status)
+                    && route.retry_budget().try_retry()
+                {
+                    event!(
+                        Level::WARN,
+                        "Retry request {} after status {status}",
+                        state.id
+                    );
+                    continue;
+                }
+                break Ok(resp);
+            }
+            Err(e) => {
+                outcome.failure();
+                attempts.push(attempt_log(
+                    state,
+                    attempt,
+                    &backend,
+                    None,
+                    Some(&e),
+                    started,
+                ));
+                if can_retry && replayable && retry.reset && route.retry_budget().try_retry() {
+                    event!(Level::WARN, "Retry request {} after error: {e}", state.id);
+                    continue;
+                }
+                break Err(e);
+            }
+        }
+    };
+    if attempts.len() > 1 {
+        access::add_attempt_logs(attempts);
+    }
+    result
+}
+
+/// 统计后端响应 body 的大小并加上读取超时
+fn wrap_incoming(
+    state: &ClientState,
+    resp: Response<Incoming>,
+    deadline: Option<Instant>,
+    idle: Option<Duration>,
+) -> Response<CResponse> {
+    resp.map(|b| {
+        CResponse::Incoming(
+            StatisticsIncoming::new(
+                state.id,
+                b,
+                crate::transport::StatisticsIncomingType::Response,
+            )
+            .with_timeout(deadline, idle),
+        )
+    })
+}
+
+// 按客户端请求生成发往后端的请求（不含 body）
+fn build_backend_request(
+    parts: &Parts,
+    state: &ClientState,
+    backend: &WebSiteBackend,
+) -> anyhow::Result<request::Builder> {
+    let pool = backend.pool();
+    // 协议版本由连接池按后端协议设置
+    let mut req = Request::builder().method(parts.method.clone());
     if let Some(v) = req.headers_mut() {
-        v.extend(origin_req.headers().clone())
+        v.extend(parts.headers.clone())
     }
     if let Some(v) = req.extensions_mut() {
-        v.extend(origin_req.extensions().clone())
+        v.extend(parts.extensions.clone())
     }
-    req = req.uri(pool.get_path().map_or_else(
-        || origin_req.uri().path().to_string(),
-        |v| {
-            let a = v.join(&origin_req.uri().path()[1..]).unwrap();
-            a.path().to_string()
-        },
-    ));
+    req = req.uri(build_target(
+        &parts.method,
+        &parts.uri,
+        pool.get_path().map(|v| v.path()),
+        state.route.rewrite(),
+    )?);
 
     // insert custom headers
     let headers = req.headers_mut().unwrap();
-    headers.insert("Host", state.host.parse()?);
+    headers.insert(
+        "Host",
+        backend
+            .inner()
+            .host
+            .as_deref()
+            .unwrap_or(&state.host)
+            .parse()?,
+    );
     headers.insert("X-Real-Ip", format!("{}", &state.remote_addr()).parse()?);
     headers.insert(
         "X-Forwarded-For",
@@ -269,19 +663,25 @@ async fn inner_handle(
     );
     headers.insert("X-Forwarded-Proto", state.scheme().to_string().parse()?);
     headers.insert("X-Forwarded-Host", state.host.parse()?);
-    let final_req = req.body(origin_req.into_body()).unwrap();
+    state.route.headers().apply_request(headers, state)?;
+    Ok(req)
+}
 
-    let mut resp = c_req.send_request(final_req).await?;
-    resp.headers_mut().insert("Server", "WebGateway".parse()?);
-    let (mut parts, b) = resp.into_parts();
-    parts.version = origin_version;
-    let final_resp = Response::from_parts(
-        parts,
-        CResponse::Incoming(StatisticsIncoming::new(
-            state.id,
-            b,
-            crate::transport::StatisticsIncomingType::Response,
-        )),
-    );
-    Ok(final_resp)
+fn attempt_log(
+    state: &ClientState,
+    attempt: usize,
+    backend: &WebSiteBackend,
+    status: Option<u16>,
+    error: Option<&anyhow::Error>,
+    started: Instant,
+) -> AccessCreateAttempt {
+    AccessCreateAttempt {
+        request_id: state.id,
+        attempt: attempt as u16,
+        backend: backend.inner().url.to_string(),
+        status,
+        error: error.map(|e| e.to_string()),
+        elapsed: started.elapsed().as_millis() as u64,
+        created_at: get_database().get_database_time().unwrap(),
+    }
 }
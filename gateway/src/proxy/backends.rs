@@ -1,30 +1,48 @@
 use std::collections::VecDeque;
 use std::net::SocketAddr;
 use std::pin::Pin;
-use std::sync::Arc;
 use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::{Arc, Mutex, RwLock};
 use std::task::{Context, Poll};
+use std::time::{Duration, Instant};
 
+use hyper::{
+    Request, Response, Uri, Version,
+    body::Incoming,
+    client::conn::{http1, http2},
+    header::HOST,
+};
+use hyper_util::rt::{TokioExecutor, TokioIo};
 use rustls::{
     ClientConfig,
     pki_types::{DnsName, ServerName},
 };
-use shared::streams::WrapperBufferStream;
-use tokio::io::{AsyncRead, AsyncWrite, AsyncWriteExt, ReadBuf};
+use shared::{
+    default::default_website_config_timeout,
+    models::websites::{DatabaseWebsiteConnectionPool, DatabaseWebsiteOutlierDetection},
+    streams::WrapperBufferStream,
+};
+use tokio::io::{AsyncRead, AsyncWrite, ReadBuf};
 use tokio::net::TcpStream;
-use tokio::sync::{Mutex, Semaphore};
+use tokio::sync::{Notify, OwnedSemaphorePermit, Semaphore};
+use tracing::{Level, event};
 use url::Url;
 
+use crate::proxy::outlier::{OutlierDetector, OutlierOutcome};
+use crate::transport::CRequest;
+
 // ---------- BackendConnection（只负责包装流，无状态）--------
 #[derive(Debug)]
 pub struct BackendConnection {
     inner: WrapperBufferStream,
+    addr: SocketAddr,
 }
 
 impl BackendConnection {
     pub async fn new_tcp(addr: SocketAddr) -> anyhow::Result<Self> {
         Ok(Self {
             inner: WrapperBufferStream::Raw(TcpStream::connect(addr).await?),
+            addr,
         })
     }
 
@@ -43,6 +61,7 @@ impl BackendConnection {
         hostname: Option<impl Into<String>>,
     ) -> anyhow::Result<Self> {
         let connector = tokio_rustls::TlsConnector::from(config);
+        let addr = stream.peer_addr()?;
         let server_name = match hostname {
             Some(h) => {
                 let host = h.into();
@@ -54,27 +73,28 @@ impl BackendConnection {
                         .into()
                 }
             }
-            None => ServerName::IpAddress(stream.peer_addr()?.ip().into()),
+            None => ServerName::IpAddress(addr.ip().into()),
         };
         Ok(Self {
             inner: WrapperBufferStream::TlsClient(Box::new(
                 connector.connect(server_name, stream).await?,
             )),
+            addr,
         })
     }
 
-    pub async fn close(self) -> anyhow::Result<()> {
-        Ok(self.inner.close().await?)
+    pub fn peer_addr(&self) -> SocketAddr {
+        self.addr
     }
 
-    /// 检查连接是否健康（这里简单示例，可自定义）
-    pub async fn is_healthy(&mut self) -> bool {
-        // 例如尝试一次零字节写（TLS 层会处理）
-        matches!(
-            tokio::time::timeout(std::time::Duration::from_millis(100), self.inner.write(&[]))
-                .await,
-            Ok(Ok(_))
-        )
+    /// TLS 连接是否协商到了 h2，明文连接返回 None
+    pub fn is_alpn_h2(&self) -> Option<bool> {
+        match &self.inner {
+            WrapperBufferStream::TlsClient(stream) => {
+                Some(stream.get_ref().1.alpn_protocol() == Some(b"h2"))
+            }
+            _ => None,
+        }
     }
 }
 
@@ -119,37 +139,50 @@ impl AsyncWrite for BackendConnection {
     }
 }
 
+// ---------- 上游协议 ----------
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum BackendProtocol {
+    #[default]
+    Http1,
+    /// TLS 连接按 ALPN 结果决定，明文连接直接使用 h2c
+    Http2,
+}
+
 // ---------- 连接池配置 ----------
 #[derive(Debug, Clone)]
 pub struct BackendConnectionPoolConfig {
     pub targets: Vec<SocketAddr>,
     pub max_connections: usize, // 改为 usize，用 0 表示无限制
+    pub max_idle_per_host: usize,
+    pub idle_timeout: Duration,
+    pub connect_timeout: Duration,
+    pub protocol: BackendProtocol,
     pub tls: bool,
     pub tls_config: Option<Arc<ClientConfig>>,
     pub hostname: Option<String>,
     pub url: Option<Url>,
+    pub outlier_detection: Option<DatabaseWebsiteOutlierDetection>,
 }
 
 impl BackendConnectionPoolConfig {
     pub fn new(target: SocketAddr) -> Self {
-        Self {
-            targets: vec![target],
-            max_connections: 0,
-            tls: false,
-            tls_config: None,
-            hostname: None,
-            url: None,
-        }
+        Self::new_from_targets(vec![target])
     }
 
     pub fn new_from_targets(targets: Vec<SocketAddr>) -> Self {
+        let defaults = DatabaseWebsiteConnectionPool::default();
         Self {
             targets,
-            max_connections: 0,
+            max_connections: defaults.max_connections,
+            max_idle_per_host: defaults.max_idle_per_host,
+            idle_timeout: Duration::from_secs(defaults.idle_timeout),
+            connect_timeout: default_website_config_timeout(),
+            protocol: BackendProtocol::default(),
             tls: false,
             tls_config: None,
             hostname: None,
             url: None,
+            outlier_detection: None,
         }
     }
 
@@ -158,6 +191,23 @@ impl BackendConnectionPoolConfig {
         self
     }
 
+    pub fn connection_pool(mut self, config: &DatabaseWebsiteConnectionPool) -> Self {
+        self.max_connections = config.max_connections;
+        self.max_idle_per_host = config.max_idle_per_host;
+        self.idle_timeout = Duration::from_secs(config.idle_timeout);
+        self
+    }
+
+    pub fn connect_timeout(mut self, timeout: Duration) -> Self {
+        self.connect_timeout = timeout;
+        self
+    }
+
+    pub fn protocol(mut self, protocol: BackendProtocol) -> Self {
+        self.protocol = protocol;
+        self
+    }
+
     pub fn tls(mut self, config: Arc<ClientConfig>, hostname: Option<String>) -> Self {
         self.tls = true;
         self.tls_config = Some(config);
@@ -169,15 +219,45 @@ impl BackendConnectionPoolConfig {
         self.url = Some(url);
         self
     }
+
+    pub fn outlier_detection(mut self, config: DatabaseWebsiteOutlierDetection) -> Self {
+        self.outlier_detection = Some(config);
+        self
+    }
+}
+
+// ---------- 已完成握手的请求句柄 ----------
+#[derive(Debug)]
+pub enum BackendSender {
+    Http1(http1::SendRequest<CRequest>),
+    Http2(http2::SendRequest<CRequest>),
+}
+
+// 空闲的 HTTP/1.1 连接（只有上一个响应读完后才会放回）
+#[derive(Debug)]
+struct IdleSender {
+    sender: http1::SendRequest<CRequest>,
+    addr: SocketAddr,
+    idle_at: Instant,
 }
 
 // ---------- 修改后的连接池 ----------
 #[derive(Debug)]
 pub struct BackendConnectionPool {
     config: BackendConnectionPoolConfig,
-    idle: Mutex<VecDeque<BackendConnection>>,
+    // DNS 重新解析后整体替换
+    targets: RwLock<Arc<Vec<SocketAddr>>>,
+    idle: Mutex<VecDeque<IdleSender>>,
+    // HTTP/2 所有请求共用一条多路复用连接
+    multiplexed: Mutex<Option<(http2::SendRequest<CRequest>, SocketAddr)>>,
+    // 同一时间只有一个请求建立 HTTP/2 连接，其余请求等待后复用
+    dialing: tokio::sync::Mutex<()>,
+    // 每条连接占用一个许可，连接关闭后归还
     semaphore: Arc<Semaphore>,
+    // 有连接放回空闲队列时唤醒等待许可的请求
+    returned: Notify,
     next_index: AtomicUsize, // 新增：轮询索引
+    outlier: Arc<OutlierDetector>,
 }
 
 impl BackendConnectionPool {
@@ -187,87 +267,271 @@ impl BackendConnectionPool {
         } else {
             config.max_connections
         };
+        let outlier = Arc::new(OutlierDetector::new(
+            config
+                .outlier_detection
+                .clone()
+                .unwrap_or(DatabaseWebsiteOutlierDetection {
+                    enabled: false,
+                    ..Default::default()
+                }),
+        ));
         Arc::new(Self {
+            outlier,
+            targets: RwLock::new(Arc::new(config.targets.clone())),
             config,
             idle: Mutex::new(VecDeque::new()),
+            multiplexed: Mutex::new(None),
+            dialing: tokio::sync::Mutex::new(()),
             semaphore: Arc::new(Semaphore::new(max)),
+            returned: Notify::new(),
             next_index: AtomicUsize::new(0),
         })
     }
 
-    /// 从池中获取一个连接
-    pub async fn get(self: &Arc<Self>) -> anyhow::Result<PooledConnection> {
-        let permit = self.semaphore.clone().acquire_owned().await?;
+    /// 从池中获取一个可以直接发送请求的句柄，没有可复用的连接时新建
+    pub async fn get(self: &Arc<Self>) -> anyhow::Result<PooledSender> {
+        let (sender, addr) = match self.config.protocol {
+            BackendProtocol::Http1 => self.idle_or_dial().await?,
+            BackendProtocol::Http2 => self.get_multiplexed().await?,
+        };
+        Ok(PooledSender {
+            sender: Some(sender),
+            addr,
+            pool: self.clone(),
+            pooled: true,
+        })
+    }
+
+    /// 新建一条不受异常检测和连接数限制影响的连接（用于健康检查），
+    /// 用完后直接关闭，不放回空闲队列
+    pub async fn connect(self: &Arc<Self>) -> anyhow::Result<PooledSender> {
+        let conn = self.create_connection().await?;
+        let addr = conn.peer_addr();
+        Ok(PooledSender {
+            sender: Some(self.handshake(conn, None).await?),
+            addr,
+            pool: self.clone(),
+            pooled: false,
+        })
+    }
 
-        // 先尝试从空闲队列取健康连接（不变）
+    /// 优先复用空闲连接，否则在连接数上限内新建；
+    /// 达到上限时等待有连接放回空闲队列或关闭
+    async fn idle_or_dial(&self) -> anyhow::Result<(BackendSender, SocketAddr)> {
         loop {
-            let mut idle = self.idle.lock().await;
-            if let Some(mut conn) = idle.pop_front() {
-                if conn.is_healthy().await {
-                    return Ok(PooledConnection {
-                        conn: Some(conn),
-                        pool: self.clone(),
-                        _permit: permit,
-                    });
-                } else {
-                    drop(conn.close().await);
-                    continue;
+            // 先登记等待再检查空闲队列，避免错过两者之间放回的连接
+            let returned = self.returned.notified();
+            tokio::pin!(returned);
+            returned.as_mut().enable();
+            if let Some(idle) = self.take_idle() {
+                return Ok((BackendSender::Http1(idle.sender), idle.addr));
+            }
+            tokio::select! {
+                permit = self.semaphore.clone().acquire_owned() => {
+                    let conn = self.try_create_connection().await?;
+                    let addr = conn.peer_addr();
+                    return Ok((self.handshake(conn, Some(permit?)).await?, addr));
                 }
+                _ = returned => {}
             }
-            break;
         }
+    }
 
-        // 没有空闲连接，创建新连接（需要从多个后端中选择）
-        let conn = self.try_create_connection().await?;
+    // 取出最近放回的空闲连接，顺便清理已关闭、已超时和被剔除地址的连接
+    fn take_idle(&self) -> Option<IdleSender> {
+        let mut idle = self.idle.lock().unwrap();
+        idle.retain(|v| self.is_reusable(v));
+        idle.pop_back()
+    }
 
-        Ok(PooledConnection {
-            conn: Some(conn),
-            pool: self.clone(),
-            _permit: permit,
+    fn put_idle(&self, sender: http1::SendRequest<CRequest>, addr: SocketAddr) {
+        let mut idle = self.idle.lock().unwrap();
+        idle.retain(|v| self.is_reusable(v));
+        if idle.len() >= self.config.max_idle_per_host {
+            return;
+        }
+        idle.push_back(IdleSender {
+            sender,
+            addr,
+            idle_at: Instant::now(),
+        });
+        self.returned.notify_waiters();
+    }
+
+    fn is_reusable(&self, idle: &IdleSender) -> bool {
+        idle.sender.is_ready()
+            && idle.idle_at.elapsed() < self.config.idle_timeout
+            && !self.outlier.is_ejected(idle.addr)
+            && self.targets().contains(&idle.addr)
+    }
+
+    pub fn targets(&self) -> Arc<Vec<SocketAddr>> {
+        self.targets.read().unwrap().clone()
+    }
+
+    /// 替换后端地址，已移除地址上的空闲连接会在下次取用时丢弃
+    pub fn set_targets(&self, targets: Vec<SocketAddr>) -> bool {
+        let mut current = self.targets.write().unwrap();
+        if **current == targets {
+            return false;
+        }
+        *current = Arc::new(targets);
+        true
+    }
+
+    fn cached_multiplexed(&self) -> Option<(http2::SendRequest<CRequest>, SocketAddr)> {
+        self.multiplexed
+            .lock()
+            .unwrap()
+            .as_ref()
+            .filter(|(sender, addr)| {
+                !sender.is_closed()
+                    && !self.outlier.is_ejected(*addr)
+                    && self.targets().contains(addr)
+            })
+            .cloned()
+    }
+
+    async fn get_multiplexed(&self) -> anyhow::Result<(BackendSender, SocketAddr)> {
+        if let Some((sender, addr)) = self.cached_multiplexed() {
+            return Ok((BackendSender::Http2(sender), addr));
+        }
+        // 只在建连期间持有 dialing，已有连接的请求不会被阻塞；
+        // 等到锁后再检查一次，复用其他请求刚建立的连接
+        let _dialing = self.dialing.lock().await;
+        if let Some((sender, addr)) = self.cached_multiplexed() {
+            return Ok((BackendSender::Http2(sender), addr));
+        }
+        // 服务端未协商 h2 时退回 HTTP/1.1 连接复用
+        let (sender, addr) = self.idle_or_dial().await?;
+        if let BackendSender::Http2(sender) = &sender {
+            *self.multiplexed.lock().unwrap() = Some((sender.clone(), addr));
+        }
+        Ok((sender, addr))
+    }
+
+    /// 按配置的协议完成握手，连接任务在后台运行直到连接关闭，
+    /// permit 随连接任务一起释放
+    pub async fn handshake(
+        &self,
+        conn: BackendConnection,
+        permit: Option<OwnedSemaphorePermit>,
+    ) -> anyhow::Result<BackendSender> {
+        let http2 = match self.config.protocol {
+            BackendProtocol::Http1 => false,
+            BackendProtocol::Http2 => conn.is_alpn_h2().unwrap_or(true),
+        };
+        Ok(match http2 {
+            true => BackendSender::Http2(self.handshake_http2(conn, permit).await?),
+            false => BackendSender::Http1(self.handshake_http1(conn, permit).await?),
         })
     }
 
-    /// 尝试连接一个后端，轮询所有地址直到成功
+    async fn handshake_http1(
+        &self,
+        conn: BackendConnection,
+        permit: Option<OwnedSemaphorePermit>,
+    ) -> anyhow::Result<http1::SendRequest<CRequest>> {
+        let addr = conn.peer_addr();
+        let (sender, connection) = http1::handshake(TokioIo::new(conn)).await?;
+        tokio::spawn(async move {
+            if let Err(e) = connection.with_upgrades().await {
+                event!(Level::DEBUG, "Backend connection {addr} closed: {e}");
+            }
+            drop(permit);
+        });
+        Ok(sender)
+    }
+
+    async fn handshake_http2(
+        &self,
+        conn: BackendConnection,
+        permit: Option<OwnedSemaphorePermit>,
+    ) -> anyhow::Result<http2::SendRequest<CRequest>> {
+        let addr = conn.peer_addr();
+        let (sender, connection) =
+            http2::handshake(TokioExecutor::new(), TokioIo::new(conn)).await?;
+        tokio::spawn(async move {
+            if let Err(e) = connection.await {
+                event!(Level::DEBUG, "Backend connection {addr} closed: {e}");
+            }
+            drop(permit);
+        });
+        Ok(sender)
+    }
+
+    /// 尝试连接一个后端，轮询所有地址直到成功，跳过被剔除的地址
     async fn try_create_connection(&self) -> anyhow::Result<BackendConnection> {
-        let targets = &self.config.targets;
+        let targets = self.targets();
         if targets.is_empty() {
             return Err(anyhow::anyhow!("No backend targets configured"));
         }
 
         // 轮询选择一个起始索引
         let start = self.next_index.fetch_add(1, Ordering::Relaxed) % targets.len();
+        let mut attempted = false;
         for i in 0..targets.len() {
             let idx = (start + i) % targets.len();
             let addr = targets[idx];
+            if !self.outlier.try_acquire(addr) {
+                continue;
+            }
+            attempted = true;
             match self.connect_to_addr(addr).await {
                 Ok(conn) => return Ok(conn),
                 Err(e) => {
                     tracing::warn!("Failed to connect to {}: {}", addr, e);
+                    self.outlier.report_failure(addr);
                     // 继续尝试下一个
                 }
             }
         }
-        Err(anyhow::anyhow!("All backends are unreachable"))
+        if attempted {
+            return Err(anyhow::anyhow!("All backends are unreachable"));
+        }
+        // 所有地址都被剔除时仍尝试一次，避免整站不可用
+        self.create_connection().await
     }
 
-    /// 根据配置连接到指定地址
-    async fn connect_to_addr(&self, addr: SocketAddr) -> anyhow::Result<BackendConnection> {
-        if self.config.tls {
-            let config = self.config.tls_config.clone().expect("TLS config missing");
-            BackendConnection::new_tls(addr, config, self.config.hostname.clone()).await
-        } else {
-            BackendConnection::new_tcp(addr).await
+    /// 不考虑异常检测状态，依次尝试所有地址
+    pub async fn create_connection(&self) -> anyhow::Result<BackendConnection> {
+        for addr in self.targets().iter() {
+            match self.connect_to_addr(*addr).await {
+                Ok(conn) => return Ok(conn),
+                Err(e) => {
+                    tracing::warn!("Failed to connect to {}: {}", addr, e);
+                }
+            }
         }
+        Err(anyhow::anyhow!("All backends are unreachable"))
     }
 
-    /// 归还连接（不变）
-    async fn return_connection(&self, mut conn: BackendConnection) {
-        if !conn.is_healthy().await {
-            let _ = conn.close().await;
-            return;
-        }
-        let mut idle = self.idle.lock().await;
-        idle.push_back(conn);
+    /// 是否还有未被剔除的地址
+    pub fn has_available_target(&self) -> bool {
+        self.targets()
+            .iter()
+            .any(|addr| !self.outlier.is_ejected(*addr))
+    }
+
+    /// 记录一次发往该地址的请求结果
+    pub fn outcome(&self, addr: SocketAddr) -> OutlierOutcome {
+        OutlierOutcome::new(self.outlier.clone(), addr)
+    }
+
+    /// 根据配置连接到指定地址，TLS 握手也计入连接超时
+    async fn connect_to_addr(&self, addr: SocketAddr) -> anyhow::Result<BackendConnection> {
+        let connect = async {
+            if self.config.tls {
+                let config = self.config.tls_config.clone().expect("TLS config missing");
+                BackendConnection::new_tls(addr, config, self.config.hostname.clone()).await
+            } else {
+                BackendConnection::new_tcp(addr).await
+            }
+        };
+        tokio::time::timeout(self.config.connect_timeout, connect)
+            .await
+            .map_err(|_| anyhow::anyhow!("Connect to {addr} timeout"))?
     }
 
     pub fn get_path(&self) -> Option<&Url> {
@@ -275,73 +539,84 @@ impl BackendConnectionPool {
     }
 }
 
-// ---------- 借出连接句柄（自动归还）---------
+// ---------- 借出的请求句柄（Drop 时自动归还）---------
 #[derive(Debug)]
-pub struct PooledConnection {
-    conn: Option<BackendConnection>, // Option 是为了能在 drop 时 move 出来
+pub struct PooledSender {
+    sender: Option<BackendSender>, // Option 是为了能在 drop 时 move 出来
+    addr: SocketAddr,
     pool: Arc<BackendConnectionPool>,
-    _permit: tokio::sync::OwnedSemaphorePermit, // 持有 permit，离开作用域时自动释放
+    pooled: bool, // 为 false 时 drop 后关闭连接
 }
 
-impl PooledConnection {
-    /// 主动归还连接（一般不需要，Drop 会自动归还）
-    pub async fn return_to_pool(mut self) -> anyhow::Result<()> {
-        if let Some(conn) = self.conn.take() {
-            self.pool.return_connection(conn).await;
-        }
-        Ok(())
+impl PooledSender {
+    pub fn peer_addr(&self) -> SocketAddr {
+        self.addr
     }
-}
 
-impl Drop for PooledConnection {
-    fn drop(&mut self) {
-        if let Some(conn) = self.conn.take() {
-            let pool = self.pool.clone();
-            // 异步归还，避免在 drop 中阻塞
-            tokio::spawn(async move {
-                pool.return_connection(conn).await;
-            });
-        }
+    pub fn is_multiplexed(&self) -> bool {
+        matches!(self.sender, Some(BackendSender::Http2(_)))
     }
-}
 
-// 委托 AsyncRead/AsyncWrite 给内部的 BackendConnection
-impl AsyncRead for PooledConnection {
-    fn poll_read(
-        mut self: Pin<&mut Self>,
-        cx: &mut Context<'_>,
-        buf: &mut ReadBuf<'_>,
-    ) -> Poll<std::io::Result<()>> {
-        Pin::new(self.conn.as_mut().unwrap()).poll_read(cx, buf)
+    /// 发送请求，HTTP/2 时补全 scheme 和 authority
+    pub async fn send_request(
+        &mut self,
+        mut req: Request<CRequest>,
+    ) -> anyhow::Result<Response<Incoming>> {
+        match self.sender.as_mut().unwrap() {
+            BackendSender::Http1(sender) => {
+                *req.version_mut() = Version::HTTP_11;
+                Ok(sender.send_request(req).await?)
+            }
+            BackendSender::Http2(sender) => {
+                *req.version_mut() = Version::HTTP_2;
+                if req.uri().scheme().is_none() {
+                    let scheme = match self.pool.config.tls {
+                        true => "https",
+                        false => "http",
+                    };
+                    let authority = match req.headers().get(HOST) {
+                        Some(host) => host.to_str()?.to_string(),
+                        None => self
+                            .pool
+                            .config
+                            .url
+                            .as_ref()
+                            .map(|v| v.authority().to_string())
+                            .unwrap_or_else(|| self.addr.to_string()),
+                    };
+                    let path = req
+                        .uri()
+                        .path_and_query()
+                        .map_or("/", |v| v.as_str())
+                        .to_string();
+                    *req.uri_mut() = Uri::builder()
+                        .scheme(scheme)
+                        .authority(authority)
+                        .path_and_query(path)
+                        .build()?;
+                }
+                Ok(sender.send_request(req).await?)
+            }
+        }
     }
 }
 
-impl AsyncWrite for PooledConnection {
-    fn poll_write(
-        mut self: Pin<&mut Self>,
-        cx: &mut Context<'_>,
-        buf: &[u8],
-    ) -> Poll<std::io::Result<usize>> {
-        Pin::new(self.conn.as_mut().unwrap()).poll_write(cx, buf)
-    }
-
-    fn poll_write_vectored(
-        mut self: Pin<&mut Self>,
-        cx: &mut Context<'_>,
-        bufs: &[std::io::IoSlice<'_>],
-    ) -> Poll<std::io::Result<usize>> {
-        Pin::new(self.conn.as_mut().unwrap()).poll_write_vectored(cx, bufs)
-    }
-
-    fn is_write_vectored(&self) -> bool {
-        self.conn.as_ref().unwrap().is_write_vectored()
-    }
-
-    fn poll_flush(mut self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<std::io::Result<()>> {
-        Pin::new(self.conn.as_mut().unwrap()).poll_flush(cx)
-    }
-
-    fn poll_shutdown(mut self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<std::io::Result<()>> {
-        Pin::new(self.conn.as_mut().unwrap()).poll_shutdown(cx)
+impl Drop for PooledSender {
+    fn drop(&mut self) {
+        // HTTP/2 句柄只是共享连接的克隆，直接丢弃即可
+        let Some(BackendSender::Http1(mut sender)) = self.sender.take() else {
+            return;
+        };
+        if !self.pooled || sender.is_closed() {
+            return;
+        }
+        let pool = self.pool.clone();
+        let addr = self.addr;
+        // 等待响应 body 读完（或连接关闭）后再决定是否放回空闲队列
+        tokio::spawn(async move {
+            if sender.ready().await.is_ok() {
+                pool.put_idle(sender, addr);
+            }
+        });
     }
 }
@@ -0,0 +1,201 @@
+use std::{
+    collections::HashMap,
+    sync::{Arc, LazyLock},
+};
+
+use argon2::{Argon2, PasswordHash, PasswordHasher, PasswordVerifier, password_hash::SaltString};
+use base64::{Engine, engine::general_purpose::STANDARD};
+use dashmap::DashSet;
+use hyper::{
+    HeaderMap, Response, StatusCode,
+    header::{AUTHORIZATION, HeaderValue, WWW_AUTHENTICATE},
+};
+use sha2::{Digest, Sha256};
+use shared::models::{access::AccessAuthStatus, websites::DatabaseWebsiteBasicAuth};
+
+use crate::{proxy::error_page, state::ClientState, transport::CResponse};
+
+// 缓存验证通过的凭据摘要，避免每个请求都计算一次慢哈希
+const VERIFIED_CAPACITY: usize = 1024;
+
+// 未知用户也校验一次占位哈希，避免通过耗时判断用户是否存在
+static DUMMY_HASH: LazyLock<Arc<PasswordHashKind>> = LazyLock::new(|| {
+    let salt = SaltString::from_b64("Z2F0ZXdheS1kdW1teQ").expect("valid salt");
+    let hash = Argon2::default()
+        .hash_password(b"", &salt)
+        .expect("hash dummy password");
+    Arc::new(PasswordHashKind::Argon2(hash.to_string()))
+});
+
+#[derive(Debug)]
+enum PasswordHashKind {
+    Argon2(String),
+    Bcrypt(String),
+}
+
+impl PasswordHashKind {
+    fn new(hash: &str) -> anyhow::Result<Self> {
+        if hash.starts_with("$argon2") {
+            PasswordHash::new(hash).map_err(|e| anyhow::anyhow!("Invalid argon2 hash: {e}"))?;
+            Ok(Self::Argon2(hash.to_string()))
+        } else if hash.starts_with("$2") {
+            Ok(Self::Bcrypt(hash.to_string()))
+        } else {
+            Err(anyhow::anyhow!(
+                "Unsupported password hash, expected argon2 or bcrypt"
+            ))
+        }
+    }
+
+    fn verify(&self, password: &str) -> bool {
+        match self {
+            Self::Argon2(hash) => PasswordHash::new(hash).is_ok_and(|hash| {
+                Argon2::default()
+                    .verify_password(password.as_bytes(), &hash)
+                    .is_ok()
+            }),
+            Self::Bcrypt(hash) => bcrypt::verify(password, hash).unwrap_or(false),
+        }
+    }
+}
+
+/// 放在响应的 extensions 中，由访问日志记录
+#[derive(Debug, Clone)]
+pub struct BasicAuthResult {
+    pub status: AccessAuthStatus,
+    pub user: Option<String>,
+}
+
+fn parse_credentials(headers: &HeaderMap) -> Option<(String, String)> {
+    let value = headers.get(AUTHORIZATION)?.to_str().ok()?;
+    let (scheme, encoded) = value.trim().split_once(' ')?;
+    if !scheme.eq_ignore_ascii_case("basic") {
+        return None;
+    }
+    let decoded = String::from_utf8(STANDARD.decode(encoded.trim()).ok()?).ok()?;
+    let (user, password) = decoded.split_once(':')?;
+    Some((user.to_string(), password.to_string()))
+}
+
+/// 按路径段匹配前缀，`/api` 不会排除 `/apikeys`
+fn is_path_excluded(path: &str, prefix: &str) -> bool {
+    path.starts_with(prefix)
+        && (prefix.ends_with('/')
+            || path.len() == prefix.len()
+            || path.as_bytes()[prefix.len()] == b'/')
+}
+
+// ---------- 路由的 Basic 认证 ----------
+#[derive(Debug)]
+pub struct BasicAuth {
+    challenge: HeaderValue,
+    users: HashMap<String, Arc<PasswordHashKind>>,
+    exclude_paths: Vec<String>,
+    verified: DashSet<[u8; 32]>,
+}
+
+impl BasicAuth {
+    /// 没有配置用户时不启用
+    pub fn new(config: &DatabaseWebsiteBasicAuth) -> anyhow::Result<Option<Self>> {
+        if config.users.is_empty() {
+            return Ok(None);
+        }
+        Ok(Some(Self {
+            challenge: HeaderValue::from_str(&format!(
+                "Basic realm=\"{}\", charset=\"UTF-8\"",
+                config.realm.replace(['"', '\\'], "")
+            ))?,
+            users: config
+                .users
+                .iter()
+                .map(|v| {
+                    Ok((
+                        v.username.clone(),
+                        Arc::new(PasswordHashKind::new(&v.password_hash)?),
+                    ))
+                })
+                .collect::<anyhow::Result<_>>()?,
+            exclude_paths: config.exclude_paths.clone(),
+            verified: DashSet::new(),
+        }))
+    }
+
+    /// 路径被排除时返回 None
+    pub async fn authenticate(&self, path: &str, headers: &HeaderMap) -> Option<BasicAuthResult> {
+        if self.exclude_paths.iter().any(|v| is_path_excluded(path, v)) {
+            return None;
+        }
+        let Some((user, password)) = parse_credentials(headers) else {
+            return Some(BasicAuthResult {
+                status: match headers.contains_key(AUTHORIZATION) {
+                    true => AccessAuthStatus::Failed,
+                    false => AccessAuthStatus::Missing,
+                },
+                user: None,
+            });
+        };
+        let digest: [u8; 32] = Sha256::digest(format!("{user}:{password}")).into();
+        let success = match self.users.get(&user) {
+            Some(_) if self.verified.contains(&digest) => true,
+            found => {
+                let hash = found.unwrap_or(&DUMMY_HASH).clone();
+                let success = tokio::task::spawn_blocking(move || hash.verify(&password))
+                    .await
+                    .unwrap_or(false)
+                    && found.is_some();
+                if success {
+                    if self.verified.len() >= VERIFIED_CAPACITY {
+                        self.verified.clear();
+                    }
+                    self.verified.insert(digest);
+                }
+                success
+            }
+        };
+        Some(BasicAuthResult {
+            status: match success {
+                true => AccessAuthStatus::Success,
+                false => AccessAuthStatus::Failed,
+            },
+            user: Some(user),
+        })
+    }
+
+    /// 返回 401 让浏览器弹出登录框
+    pub fn unauthorized(
+        &self,
+        state: &ClientState,
+        headers: &HeaderMap,
+        result: BasicAuthResult,
+    ) -> Response<CResponse> {
+        let mut resp = error_page::error_response(
+            StatusCode::UNAUTHORIZED,
+            &state.id,
+            error_page::prefers_json(headers),
+            Some(&state.website.inner().config.error_pages),
+        );
+        resp.headers_mut()
+            .insert(WWW_AUTHENTICATE, self.challenge.clone());
+        resp.extensions_mut().insert(result);
+        resp
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn excludes_on_segment_boundary() {
+        assert!(is_path_excluded("/api", "/api"));
+        assert!(is_path_excluded("/api/v1", "/api"));
+        assert!(is_path_excluded("/api/v1", "/api/"));
+        assert!(!is_path_excluded("/apikeys", "/api"));
+        assert!(!is_path_excluded("/ap", "/api"));
+    }
+
+    #[test]
+    fn dummy_hash_rejects_passwords() {
+        assert!(!DUMMY_HASH.verify("password"));
+    }
+}
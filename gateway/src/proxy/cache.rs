@@ -0,0 +1,1000 @@
+use std::{
+    collections::{BTreeMap, HashMap},
+    fs::File,
+    hash::{DefaultHasher, Hash, Hasher},
+    io::{BufRead, BufReader},
+    path::{Path, PathBuf},
+    sync::{Arc, Mutex},
+    time::{Duration, SystemTime, UNIX_EPOCH},
+};
+
+use bytes::{Bytes, BytesMut};
+use dashmap::DashMap;
+use futures_util::{StreamExt, TryStreamExt};
+use http_body_util::{BodyExt, StreamBody};
+use hyper::{
+    HeaderMap, Method, Response, StatusCode,
+    body::Frame,
+    header::{
+        AGE, AUTHORIZATION, CACHE_CONTROL, CONNECTION, CONTENT_LENGTH, CONTENT_LOCATION, DATE,
+        ETAG, EXPIRES, HeaderName, HeaderValue, IF_MATCH, IF_MODIFIED_SINCE, IF_NONE_MATCH,
+        IF_RANGE, IF_UNMODIFIED_SINCE, LAST_MODIFIED, PRAGMA, RANGE, SET_COOKIE, TE, TRAILER,
+        TRANSFER_ENCODING, UPGRADE, VARY,
+    },
+    http::request::Parts,
+};
+use serde::{Deserialize, Serialize};
+use shared::{
+    models::{access::AccessCacheStatus, websites::DatabaseWebsiteCache},
+    objectid::ObjectId,
+};
+use tokio::{sync::OwnedMutexGuard, time::Instant};
+use tracing::{Level, event};
+
+use crate::{
+    proxy::{retry::RetryBody, send_to_backend, wrap_incoming},
+    state::ClientState,
+    transport::CResponse,
+};
+
+/// 后台刷新没有客户端的超时可用
+const REFRESH_TIMEOUT: Duration = Duration::from_secs(60);
+
+/// 可以缓存的状态码（默认可缓存的状态码中网关会返回的部分）
+const CACHEABLE_STATUS: [u16; 7] = [200, 203, 204, 300, 301, 404, 410];
+
+/// 逐跳头部不能保存
+const HOP_BY_HOP: [HeaderName; 5] = [CONNECTION, TE, TRAILER, TRANSFER_ENCODING, UPGRADE];
+
+// ---------- Cache-Control ----------
+#[derive(Debug, Default)]
+struct CacheControl {
+    no_store: bool,
+    no_cache: bool,
+    private: bool,
+    must_revalidate: bool,
+    max_age: Option<u64>,
+    s_maxage: Option<u64>,
+    stale_while_revalidate: Option<u64>,
+}
+
+impl CacheControl {
+    fn parse(headers: &HeaderMap) -> Self {
+        let mut result = Self::default();
+        for directive in headers
+            .get_all(CACHE_CONTROL)
+            .iter()
+            .filter_map(|v| v.to_str().ok())
+            .flat_map(|v| v.split(','))
+        {
+            let (name, value) = directive.split_once('=').unwrap_or((directive, ""));
+            let value = value.trim().trim_matches('"').parse::<u64>().ok();
+            match name.trim().to_ascii_lowercase().as_str() {
+                "no-store" => result.no_store = true,
+                "no-cache" => result.no_cache = true,
+                "private" => result.private = true,
+                "must-revalidate" | "proxy-revalidate" => result.must_revalidate = true,
+                "max-age" => result.max_age = value,
+                "s-maxage" => result.s_maxage = value,
+                "stale-while-revalidate" => result.stale_while_revalidate = value,
+                _ => {}
+            }
+        }
+        result
+    }
+}
+
+fn parse_date(headers: &HeaderMap, name: HeaderName) -> Option<SystemTime> {
+    headers
+        .get(name)
+        .and_then(|v| v.to_str().ok())
+        .and_then(|v| httpdate::parse_http_date(v).ok())
+}
+
+/// Vary 中的头部名称，包含 `*` 时返回 None
+fn vary_names(headers: &HeaderMap) -> Option<Vec<HeaderName>> {
+    let mut names = vec![];
+    for name in headers
+        .get_all(VARY)
+        .iter()
+        .filter_map(|v| v.to_str().ok())
+        .flat_map(|v| v.split(','))
+        .map(|v| v.trim())
+        .filter(|v| !v.is_empty())
+    {
+        if name == "*" {
+            return None;
+        }
+        if let Ok(name) = HeaderName::from_bytes(name.as_bytes())
+            && !names.contains(&name)
+        {
+            names.push(name);
+        }
+    }
+    Some(names)
+}
+
+/// 同一个地址按 Vary 中头部的值区分不同的响应
+fn variant_key(key: &str, names: &[HeaderName], headers: &HeaderMap) -> String {
+    let mut result = key.to_string();
+    for name in names {
+        result.push('\n');
+        let values = headers
+            .get_all(name)
+            .iter()
+            .map(|v| String::from_utf8_lossy(v.as_bytes()).into_owned())
+            .collect::<Vec<_>>();
+        result.push_str(&values.join(","));
+    }
+    result
+}
+
+/// If-None-Match 优先于 If-Modified-Since
+fn is_not_modified(req: &HeaderMap, resp: &HeaderMap) -> bool {
+    if let Some(value) = req.get(IF_NONE_MATCH).and_then(|v| v.to_str().ok()) {
+        let Some(etag) = resp.get(ETAG).and_then(|v| v.to_str().ok()) else {
+            return false;
+        };
+        let etag = etag.trim_start_matches("W/");
+        return value
+            .split(',')
+            .map(|v| v.trim().trim_start_matches("W/"))
+            .any(|v| v == "*" || v == etag);
+    }
+    match (
+        parse_date(req, IF_MODIFIED_SINCE),
+        parse_date(resp, LAST_MODIFIED),
+    ) {
+        (Some(since), Some(modified)) => {
+            httpdate::HttpDate::from(modified) <= httpdate::HttpDate::from(since)
+        }
+        _ => false,
+    }
+}
+
+// ---------- 缓存的响应 ----------
+#[derive(Debug)]
+pub struct CachedResponse {
+    status: StatusCode,
+    headers: HeaderMap,
+    body: Bytes,
+    stored_at: SystemTime,
+    /// 存入时后端给出的 Age
+    initial_age: u64,
+    lifetime: u64,
+    stale_while_revalidate: u64,
+}
+
+impl CachedResponse {
+    fn age(&self) -> u64 {
+        self.initial_age + self.stored_at.elapsed().unwrap_or_default().as_secs()
+    }
+
+    fn is_fresh(&self) -> bool {
+        self.age() < self.lifetime
+    }
+
+    fn is_usable_stale(&self) -> bool {
+        self.age() < self.lifetime + self.stale_while_revalidate
+    }
+
+    fn size(&self) -> u64 {
+        let headers = self
+            .headers
+            .iter()
+            .map(|(k, v)| k.as_str().len() + v.len())
+            .sum::<usize>();
+        (self.body.len() + headers) as u64
+    }
+
+    /// 按客户端的条件请求返回 304，HEAD 请求不返回 body
+    fn serve(&self, parts: &Parts, status: AccessCacheStatus) -> Response<CResponse> {
+        let not_modified =
+            self.status == StatusCode::OK && is_not_modified(&parts.headers, &self.headers);
+        let body = match not_modified || parts.method == Method::HEAD {
+            true => Bytes::new(),
+            false => self.body.clone(),
+        };
+        let mut resp = Response::new(CResponse::from(body));
+        match not_modified {
+            true => {
+                *resp.status_mut() = StatusCode::NOT_MODIFIED;
+                for name in [
+                    CACHE_CONTROL,
+                    CONTENT_LOCATION,
+                    DATE,
+                    ETAG,
+                    EXPIRES,
+                    LAST_MODIFIED,
+                    VARY,
+                ] {
+                    for value in self.headers.get_all(&name) {
+                        resp.headers_mut().append(name.clone(), value.clone());
+                    }
+                }
+            }
+            false => {
+                *resp.status_mut() = self.status;
+                *resp.headers_mut() = self.headers.clone();
+            }
+        }
+        resp.headers_mut()
+            .insert(AGE, HeaderValue::from(self.age()));
+        mark(&mut resp, status);
+        resp
+    }
+}
+
+/// 在响应上记录缓存状态，访问日志从 extensions 中读取
+fn mark(resp: &mut Response<CResponse>, status: AccessCacheStatus) {
+    resp.extensions_mut().insert(status);
+}
+
+// ---------- LRU ----------
+#[derive(Debug)]
+struct LruEntry<V> {
+    value: V,
+    tick: u64,
+    size: u64,
+}
+
+/// 按大小限制容量，超出时淘汰最久未使用的条目
+#[derive(Debug)]
+struct Lru<V> {
+    entries: HashMap<String, LruEntry<V>>,
+    order: BTreeMap<u64, String>,
+    tick: u64,
+    size: u64,
+    capacity: u64,
+}
+
+impl<V> Lru<V> {
+    fn new(capacity: u64) -> Self {
+        Self {
+            entries: HashMap::new(),
+            order: BTreeMap::new(),
+            tick: 0,
+            size: 0,
+            capacity,
+        }
+    }
+
+    fn get(&mut self, key: &str) -> Option<&V> {
+        let entry = self.entries.get_mut(key)?;
+        self.order.remove(&entry.tick);
+        self.tick += 1;
+        entry.tick = self.tick;
+        self.order.insert(self.tick, key.to_string());
+        Some(&entry.value)
+    }
+
+    fn remove(&mut self, key: &str) -> Option<V> {
+        let entry = self.entries.remove(key)?;
+        self.order.remove(&entry.tick);
+        self.size -= entry.size;
+        Some(entry.value)
+    }
+
+    /// 返回被淘汰的条目
+    fn insert(&mut self, key: String, value: V, size: u64) -> Vec<(String, V)> {
+        self.remove(&key);
+        self.tick += 1;
+        self.order.insert(self.tick, key.clone());
+        self.entries.insert(
+            key,
+            LruEntry {
+                value,
+                tick: self.tick,
+                size,
+            },
+        );
+        self.size += size;
+        let mut evicted = vec![];
+        while self.size > self.capacity
+            && let Some((_, key)) = self.order.pop_first()
+        {
+            if let Some(entry) = self.entries.remove(&key) {
+                self.size -= entry.size;
+                evicted.push((key, entry.value));
+            }
+        }
+        evicted
+    }
+}
+
+#[derive(Debug, Clone)]
+enum Slot {
+    /// 后端声明了 Vary，响应保存在 variant_key 下
+    Vary(Vec<HeaderName>),
+    Response(Arc<CachedResponse>),
+}
+
+// ---------- 磁盘层 ----------
+#[derive(Debug, Serialize, Deserialize)]
+struct DiskMeta {
+    key: String,
+    status: u16,
+    headers: Vec<(String, Vec<u8>)>,
+    /// UNIX 时间戳，单位为秒
+    stored_at: u64,
+    initial_age: u64,
+    lifetime: u64,
+    stale_while_revalidate: u64,
+}
+
+fn disk_file(dir: &Path, key: &str) -> PathBuf {
+    let mut hasher = DefaultHasher::new();
+    key.hash(&mut hasher);
+    dir.join(format!("{:016x}", hasher.finish()))
+}
+
+/// 文件内容为一行 JSON 元数据加上 body
+fn encode_disk(key: &str, entry: &CachedResponse) -> anyhow::Result<Vec<u8>> {
+    let meta = DiskMeta {
+        key: key.to_string(),
+        status: entry.status.as_u16(),
+        headers: entry
+            .headers
+            .iter()
+            .map(|(k, v)| (k.to_string(), v.as_bytes().to_vec()))
+            .collect(),
+        stored_at: entry
+            .stored_at
+            .duration_since(UNIX_EPOCH)
+            .unwrap_or_default()
+            .as_secs(),
+        initial_age: entry.initial_age,
+        lifetime: entry.lifetime,
+        stale_while_revalidate: entry.stale_while_revalidate,
+    };
+    let mut data = serde_json::to_vec(&meta)?;
+    data.push(b'\n');
+    data.extend_from_slice(&entry.body);
+    Ok(data)
+}
+
+fn decode_disk(key: &str, data: Vec<u8>) -> anyhow::Result<CachedResponse> {
+    let split = data
+        .iter()
+        .position(|v| *v == b'\n')
+        .ok_or(anyhow::anyhow!("Invalid cache file"))?;
+    let meta: DiskMeta = serde_json::from_slice(&data[..split])?;
+    if meta.key != key {
+        return Err(anyhow::anyhow!("Cache file belongs to another key"));
+    }
+    let mut headers = HeaderMap::new();
+    for (name, value) in meta.headers {
+        headers.append(
+            HeaderName::from_bytes(name.as_bytes())?,
+            HeaderValue::from_bytes(&value)?,
+        );
+    }
+    let mut body = Bytes::from(data);
+    Ok(CachedResponse {
+        status: StatusCode::from_u16(meta.status)?,
+        headers,
+        body: body.split_off(split + 1),
+        stored_at: UNIX_EPOCH + Duration::from_secs(meta.stored_at),
+        initial_age: meta.initial_age,
+        lifetime: meta.lifetime,
+        stale_while_revalidate: meta.stale_while_revalidate,
+    })
+}
+
+/// 每个路由固定的磁盘目录，重新加载配置后继续使用
+fn disk_dir(path: &str, website_id: ObjectId, route_id: &str) -> PathBuf {
+    Path::new(path).join(website_id.to_string()).join(route_id)
+}
+
+/// 只读取第一行的元数据
+fn read_disk_key(path: &Path) -> Option<String> {
+    let mut line = Vec::new();
+    BufReader::new(File::open(path).ok()?)
+        .read_until(b'\n', &mut line)
+        .ok()?;
+    serde_json::from_slice::<DiskMeta>(line.strip_suffix(b"\n")?)
+        .ok()
+        .map(|v| v.key)
+}
+
+/// 按修改时间从旧到新载入已有的文件，无法识别的文件直接删除
+fn load_disk_index(dir: &Path, index: &Mutex<Lru<()>>) {
+    let Ok(entries) = std::fs::read_dir(dir) else {
+        return;
+    };
+    let mut files = entries
+        .filter_map(|v| v.ok())
+        .filter_map(|v| {
+            let metadata = v.metadata().ok()?;
+            metadata.is_file().then(|| {
+                (
+                    metadata.modified().unwrap_or(UNIX_EPOCH),
+                    metadata.len(),
+                    v.path(),
+                )
+            })
+        })
+        .collect::<Vec<_>>();
+    files.sort_by_key(|(modified, _, _)| *modified);
+    for (_, size, path) in files {
+        match read_disk_key(&path) {
+            Some(key) if disk_file(dir, &key) == path => {
+                let evicted = index.lock().unwrap().insert(key, (), size);
+                for (key, _) in evicted {
+                    let _ = std::fs::remove_file(disk_file(dir, &key));
+                }
+            }
+            _ => {
+                let _ = std::fs::remove_file(&path);
+            }
+        }
+    }
+}
+
+/// 关闭缓存后删除路由的磁盘目录
+pub async fn remove_disk_cache(
+    config: &DatabaseWebsiteCache,
+    website_id: ObjectId,
+    route_id: &str,
+) {
+    let Some(path) = &config.disk_path else {
+        return;
+    };
+    let dir = disk_dir(path, website_id, route_id);
+    if let Err(e) = tokio::fs::remove_dir_all(&dir).await
+        && e.kind() != std::io::ErrorKind::NotFound
+    {
+        event!(
+            Level::WARN,
+            "Failed to remove cache directory {}: {e}",
+            dir.display()
+        );
+    }
+}
+
+/// 内存中淘汰的响应，目录在重新加载配置后保留，只在关闭缓存时删除
+#[derive(Debug)]
+struct DiskStore {
+    dir: PathBuf,
+    index: Arc<Mutex<Lru<()>>>,
+}
+
+impl DiskStore {
+    fn new(dir: PathBuf, capacity: u64) -> anyhow::Result<Self> {
+        std::fs::create_dir_all(&dir)?;
+        let index = Arc::new(Mutex::new(Lru::new(capacity)));
+        // 在后台重建索引，期间未载入的文件视为未命中
+        {
+            let dir = dir.clone();
+            let index = index.clone();
+            tokio::task::spawn_blocking(move || load_disk_index(&dir, &index));
+        }
+        Ok(Self { dir, index })
+    }
+
+    fn save(&self, key: String, entry: Arc<CachedResponse>) {
+        let dir = self.dir.clone();
+        let index = self.index.clone();
+        tokio::task::spawn_blocking(move || {
+            let result = encode_disk(&key, &entry).and_then(|data| {
+                std::fs::write(disk_file(&dir, &key), &data)?;
+                Ok(data.len() as u64)
+            });
+            match result {
+                Ok(size) => {
+                    let evicted = index.lock().unwrap().insert(key, (), size);
+                    for (key, _) in evicted {
+                        let _ = std::fs::remove_file(disk_file(&dir, &key));
+                    }
+                }
+                Err(e) => event!(Level::WARN, "Failed to write cache file: {e}"),
+            }
+        });
+    }
+
+    /// 读取后从磁盘移除，由调用者放回内存
+    async fn take(&self, key: &str) -> Option<CachedResponse> {
+        self.index.lock().unwrap().remove(key)?;
+        let path = disk_file(&self.dir, key);
+        let data = tokio::fs::read(&path).await.ok();
+        let _ = tokio::fs::remove_file(&path).await;
+        decode_disk(key, data?).ok()
+    }
+}
+
+// ---------- 合并并发回源 ----------
+/// 持有期间同一个 key 的其他请求等待，而不是同时回源
+struct FillGuard {
+    cache: Arc<ResponseCache>,
+    key: String,
+    lock: Option<OwnedMutexGuard<()>>,
+}
+
+impl Drop for FillGuard {
+    fn drop(&mut self) {
+        self.lock.take();
+        self.cache.release(&self.key);
+    }
+}
+
+enum Fill {
+    Leader(FillGuard),
+    Wait(Arc<tokio::sync::Mutex<()>>),
+}
+
+// ---------- 响应缓存 ----------
+#[derive(Debug)]
+pub struct ResponseCache {
+    config: DatabaseWebsiteCache,
+    memory: Mutex<Lru<Slot>>,
+    disk: Option<DiskStore>,
+    filling: DashMap<String, Arc<tokio::sync::Mutex<()>>>,
+}
+
+impl ResponseCache {
+    pub fn new(
+        config: &DatabaseWebsiteCache,
+        website_id: ObjectId,
+        route_id: &str,
+    ) -> anyhow::Result<Self> {
+        Ok(Self {
+            config: config.clone(),
+            memory: Mutex::new(Lru::new(config.max_memory)),
+            disk: config
+                .disk_path
+                .as_deref()
+                .map(|v| DiskStore::new(disk_dir(v, website_id, route_id), config.max_disk))
+                .transpose()?,
+            filling: DashMap::new(),
+        })
+    }
+
+    /// 只缓存没有 body、不带认证和 Range 的 GET / HEAD 请求
+    pub fn is_cacheable_request(&self, parts: &Parts) -> bool {
+        matches!(parts.method, Method::GET | Method::HEAD)
+            && !parts.headers.contains_key(AUTHORIZATION)
+            && !parts.headers.contains_key(RANGE)
+            && !CacheControl::parse(&parts.headers).no_store
+    }
+
+    /// 按位置选择的后端组可能返回不同内容，组的序号也作为键的一部分
+    fn key(state: &ClientState, parts: &Parts) -> String {
+        format!(
+            "{}://{}{}#{}",
+            state.scheme(),
+            state.host(),
+            parts.uri.path_and_query().map_or("/", |v| v.as_str()),
+            state
+                .route
+                .select_group(state.base.location())
+                .map_or(0, |(index, _)| index)
+        )
+    }
+
+    fn release(&self, key: &str) {
+        self.filling
+            .remove_if(key, |_, v| Arc::strong_count(v) == 1);
+    }
+
+    fn fill(self: &Arc<Self>, key: &str) -> Fill {
+        let lock = self.filling.entry(key.to_string()).or_default().clone();
+        match lock.clone().try_lock_owned() {
+            Ok(guard) => Fill::Leader(FillGuard {
+                cache: self.clone(),
+                key: key.to_string(),
+                lock: Some(guard),
+            }),
+            Err(_) => Fill::Wait(lock),
+        }
+    }
+
+    fn insert(&self, key: String, slot: Slot, size: u64) {
+        let evicted = self.memory.lock().unwrap().insert(key, slot, size);
+        if let Some(disk) = &self.disk {
+            for (key, slot) in evicted {
+                if let Slot::Response(entry) = slot {
+                    disk.save(key, entry);
+                }
+            }
+        }
+    }
+
+    async fn get(&self, key: &str) -> Option<Arc<CachedResponse>> {
+        let slot = self.memory.lock().unwrap().get(key).cloned();
+        match slot {
+            Some(Slot::Response(entry)) => Some(entry),
+            Some(Slot::Vary(_)) => None,
+            None => {
+                let entry = Arc::new(self.disk.as_ref()?.take(key).await?);
+                self.insert(key.to_string(), Slot::Response(entry.clone()), entry.size());
+                Some(entry)
+            }
+        }
+    }
+
+    async fn lookup(&self, key: &str, headers: &HeaderMap) -> Option<Arc<CachedResponse>> {
+        let slot = self.memory.lock().unwrap().get(key).cloned();
+        match slot {
+            Some(Slot::Vary(names)) => self.get(&variant_key(key, &names, headers)).await,
+            _ => self.get(key).await,
+        }
+    }
+
+    fn store(
+        &self,
+        key: &str,
+        req_headers: &HeaderMap,
+        resp_headers: &HeaderMap,
+        entry: Arc<CachedResponse>,
+    ) {
+        let size = entry.size();
+        match vary_names(resp_headers) {
+            Some(names) if !names.is_empty() => {
+                let variant = variant_key(key, &names, req_headers);
+                self.insert(key.to_string(), Slot::Vary(names), key.len() as u64);
+                self.insert(variant, Slot::Response(entry), size);
+            }
+            _ => self.insert(key.to_string(), Slot::Response(entry), size),
+        }
+    }
+
+    /// 可以保存时返回 (新鲜时间, 过期后仍可使用的时间)
+    fn policy(
+        &self,
+        method: &Method,
+        status: StatusCode,
+        headers: &HeaderMap,
+    ) -> Option<(u64, u64)> {
+        let cc = CacheControl::parse(headers);
+        if *method != Method::GET
+            || !CACHEABLE_STATUS.contains(&status.as_u16())
+            || cc.no_store
+            || cc.private
+            || headers.contains_key(SET_COOKIE)
+            || vary_names(headers).is_none()
+            || headers
+                .get(CONTENT_LENGTH)
+                .and_then(|v| v.to_str().ok())
+                .and_then(|v| v.parse::<u64>().ok())
+                .is_some_and(|v| v > self.config.max_object_size)
+        {
+            return None;
+        }
+        let has_validator = headers.contains_key(ETAG) || headers.contains_key(LAST_MODIFIED);
+        let explicit = cc.s_maxage.or(cc.max_age).or_else(|| {
+            let expires = parse_date(headers, EXPIRES);
+            // 无法解析的 Expires 视为已过期
+            if expires.is_none() && headers.contains_key(EXPIRES) {
+                return Some(0);
+            }
+            let date = parse_date(headers, DATE).unwrap_or_else(SystemTime::now);
+            expires.map(|v| v.duration_since(date).unwrap_or_default().as_secs())
+        });
+        let lifetime = match cc.no_cache {
+            true => 0,
+            false => {
+                explicit.or((self.config.default_ttl > 0).then_some(self.config.default_ttl))?
+            }
+        };
+        // 每次都需要验证的响应没有校验器就没有意义
+        if lifetime == 0 && !has_validator {
+            return None;
+        }
+        let stale_while_revalidate = match cc.no_cache || cc.must_revalidate {
+            true => 0,
+            false => cc
+                .stale_while_revalidate
+                .unwrap_or(self.config.stale_while_revalidate),
+        };
+        Some((lifetime, stale_while_revalidate))
+    }
+
+    fn entry(
+        status: StatusCode,
+        headers: &HeaderMap,
+        body: Bytes,
+        (lifetime, stale_while_revalidate): (u64, u64),
+    ) -> CachedResponse {
+        let mut headers = headers.clone();
+        for name in HOP_BY_HOP {
+            headers.remove(name);
+        }
+        headers.remove("keep-alive");
+        let initial_age = headers
+            .remove(AGE)
+            .and_then(|v| v.to_str().ok()?.parse::<u64>().ok())
+            .unwrap_or_default();
+        CachedResponse {
+            status,
+            headers,
+            body,
+            stored_at: SystemTime::now(),
+            initial_age,
+            lifetime,
+            stale_while_revalidate,
+        }
+    }
+
+    /// 后端返回 304 时用新的头部更新缓存
+    fn refresh(
+        &self,
+        key: &str,
+        req_headers: &HeaderMap,
+        entry: &CachedResponse,
+        headers: &HeaderMap,
+    ) -> Arc<CachedResponse> {
+        let mut merged = entry.headers.clone();
+        for name in headers.keys() {
+            if *name == CONTENT_LENGTH || HOP_BY_HOP.contains(name) {
+                continue;
+            }
+            merged.remove(name);
+            for value in headers.get_all(name) {
+                merged.append(name.clone(), value.clone());
+            }
+        }
+        let policy = self.policy(&Method::GET, entry.status, &merged);
+        let refreshed = Arc::new(Self::entry(
+            entry.status,
+            &merged,
+            entry.body.clone(),
+            policy.unwrap_or_default(),
+        ));
+        match policy {
+            Some(_) => self.store(key, req_headers, &merged, refreshed.clone()),
+            None => {
+                self.memory.lock().unwrap().remove(key);
+            }
+        }
+        refreshed
+    }
+
+    pub async fn handle(
+        self: &Arc<Self>,
+        state: &ClientState,
+        parts: &Parts,
+        deadline: Option<Instant>,
+        idle: Option<Duration>,
+    ) -> anyhow::Result<Response<CResponse>> {
+        let key = Self::key(state, parts);
+        // 客户端要求验证时不能直接使用缓存
+        let revalidate = CacheControl::parse(&parts.headers).no_cache
+            || parts
+                .headers
+                .get(PRAGMA)
+                .is_some_and(|v| v.as_bytes().eq_ignore_ascii_case(b"no-cache"));
+        let cached = self.lookup(&key, &parts.headers).await;
+        if let Some(entry) = &cached
+            && !revalidate
+        {
+            if entry.is_fresh() {
+                return Ok(entry.serve(parts, AccessCacheStatus::Hit));
+            }
+            if entry.is_usable_stale() {
+                self.spawn_refresh(state, parts, key, entry.clone());
+                return Ok(entry.serve(parts, AccessCacheStatus::Stale));
+            }
+        }
+        if parts.method != Method::GET {
+            return self.fetch(state, parts, &key, None, deadline, idle).await;
+        }
+
+        let _guard = match self.fill(&key) {
+            Fill::Leader(guard) => Some(guard),
+            Fill::Wait(lock) => {
+                drop(lock.lock().await);
+                drop(lock);
+                self.release(&key);
+                // 其他请求已经回源，结果可以直接使用
+                if let Some(entry) = self.lookup(&key, &parts.headers).await
+                    && entry.is_fresh()
+                {
+                    return Ok(entry.serve(parts, AccessCacheStatus::Hit));
+                }
+                None
+            }
+        };
+        self.fetch(state, parts, &key, cached, deadline, idle).await
+    }
+
+    fn spawn_refresh(
+        self: &Arc<Self>,
+        state: &ClientState,
+        parts: &Parts,
+        key: String,
+        entry: Arc<CachedResponse>,
+    ) {
+        let Fill::Leader(guard) = self.fill(&key) else {
+            return;
+        };
+        let cache = self.clone();
+        let state = state.clone();
+        let parts = parts.clone();
+        tokio::spawn(async move {
+            let _guard = guard;
+            let deadline = Some(Instant::now() + REFRESH_TIMEOUT);
+            if let Err(e) = cache
+                .fetch(&state, &parts, &key, Some(entry), deadline, None)
+                .await
+            {
+                event!(Level::WARN, "Failed to refresh cache {key}: {e}");
+            }
+        });
+    }
+
+    /// 回源，有旧的缓存时发送条件请求
+    async fn fetch(
+        &self,
+        state: &ClientState,
+        parts: &Parts,
+        key: &str,
+        cached: Option<Arc<CachedResponse>>,
+        deadline: Option<Instant>,
+        idle: Option<Duration>,
+    ) -> anyhow::Result<Response<CResponse>> {
+        let mut req_parts = parts.clone();
+        let cached = cached.filter(|_| parts.method == Method::GET);
+        if parts.method == Method::GET {
+            // 缓存完整的响应，客户端的条件请求由网关处理
+            for name in [
+                IF_MATCH,
+                IF_NONE_MATCH,
+                IF_MODIFIED_SINCE,
+                IF_UNMODIFIED_SINCE,
+                IF_RANGE,
+            ] {
+                req_parts.headers.remove(name);
+            }
+            if let Some(entry) = &cached {
+                if let Some(etag) = entry.headers.get(ETAG) {
+                    req_parts.headers.insert(IF_NONE_MATCH, etag.clone());
+                }
+                if let Some(modified) = entry.headers.get(LAST_MODIFIED) {
+                    req_parts
+                        .headers
+                        .insert(IF_MODIFIED_SINCE, modified.clone());
+                }
+            }
+        }
+        let mut body = RetryBody::Replay(Bytes::new());
+        let resp = send_to_backend(state, &req_parts, &mut body, true, false).await?;
+
+        if resp.status() == StatusCode::NOT_MODIFIED
+            && let Some(entry) = &cached
+        {
+            let entry = self.refresh(key, &parts.headers, entry, resp.headers());
+            return Ok(entry.serve(parts, AccessCacheStatus::Revalidated));
+        }
+        let Some(policy) = self.policy(&parts.method, resp.status(), resp.headers()) else {
+            if parts.method == Method::GET {
+                self.memory.lock().unwrap().remove(key);
+            }
+            let mut resp = wrap_incoming(state, resp, deadline, idle);
+            mark(&mut resp, AccessCacheStatus::Miss);
+            return Ok(resp);
+        };
+        let (resp_parts, body) = wrap_incoming(state, resp, deadline, idle).into_parts();
+        match collect_limited(body, self.config.max_object_size).await? {
+            Ok(body) => {
+                let entry = Arc::new(Self::entry(
+                    resp_parts.status,
+                    &resp_parts.headers,
+                    body,
+                    policy,
+                ));
+                self.store(key, &parts.headers, &resp_parts.headers, entry.clone());
+                Ok(entry.serve(parts, AccessCacheStatus::Miss))
+            }
+            Err(body) => {
+                let mut resp = Response::from_parts(resp_parts, body);
+                mark(&mut resp, AccessCacheStatus::Miss);
+                Ok(resp)
+            }
+        }
+    }
+}
+
+/// 读取不超过 limit 的 body，超过时返回包含已读部分的流
+async fn collect_limited(
+    mut body: CResponse,
+    limit: u64,
+) -> anyhow::Result<Result<Bytes, CResponse>> {
+    let mut collected = BytesMut::new();
+    while let Some(frame) = body.frame().await {
+        let Ok(data) = frame.map_err(|e| anyhow::anyhow!(e))?.into_data() else {
+            continue;
+        };
+        collected.extend_from_slice(&data);
+        if collected.len() as u64 > limit {
+            let head = futures_util::stream::once(async move {
+                Ok::<_, Box<dyn std::error::Error + Send + Sync>>(collected.freeze())
+            });
+            let stream = head.chain(body.into_data_stream()).map_ok(Frame::data);
+            return Ok(Err(CResponse::Stream(
+                StreamBody::new(stream).boxed_unsync(),
+            )));
+        }
+    }
+    Ok(Ok(collected.freeze()))
+}
+
+#[cfg(test)]
+mod tests {
+    use hyper::header::HeaderValue;
+
+    use super::*;
+
+    fn headers(name: HeaderName, values: &[&'static str]) -> HeaderMap {
+        let mut headers = HeaderMap::new();
+        for value in values {
+            headers.append(name.clone(), HeaderValue::from_static(value));
+        }
+        headers
+    }
+
+    #[test]
+    fn parses_cache_control() {
+        let cc = CacheControl::parse(&headers(
+            CACHE_CONTROL,
+            &[
+                "public, Max-Age=60, s-maxage=\"120\"",
+                "stale-while-revalidate=30",
+            ],
+        ));
+        assert_eq!(cc.max_age, Some(60));
+        assert_eq!(cc.s_maxage, Some(120));
+        assert_eq!(cc.stale_while_revalidate, Some(30));
+        assert!(!cc.no_store && !cc.no_cache && !cc.private && !cc.must_revalidate);
+    }
+
+    #[test]
+    fn parses_cache_control_flags() {
+        let cc = CacheControl::parse(&headers(
+            CACHE_CONTROL,
+            &["no-store, no-cache, private, proxy-revalidate, max-age=abc"],
+        ));
+        assert!(cc.no_store && cc.no_cache && cc.private && cc.must_revalidate);
+        assert_eq!(cc.max_age, None);
+        assert!(!CacheControl::parse(&HeaderMap::new()).no_store);
+    }
+
+    #[test]
+    fn parses_vary() {
+        let names = vary_names(&headers(
+            VARY,
+            &["Accept-Encoding, accept-encoding", "Origin"],
+        ));
+        assert_eq!(
+            names,
+            Some(vec![
+                HeaderName::from_static("accept-encoding"),
+                HeaderName::from_static("origin")
+            ])
+        );
+        assert_eq!(vary_names(&headers(VARY, &["Origin, *"])), None);
+    }
+
+    #[test]
+    fn reloads_disk_index() {
+        let dir = std::env::temp_dir().join(format!("gateway-cache-{}", ObjectId::new()));
+        std::fs::create_dir_all(&dir).unwrap();
+        let entry = CachedResponse {
+            status: StatusCode::OK,
+            headers: HeaderMap::new(),
+            body: Bytes::from_static(b"hello"),
+            stored_at: SystemTime::now(),
+            initial_age: 0,
+            lifetime: 60,
+            stale_while_revalidate: 0,
+        };
+        let key = "http://example.com/#0";
+        std::fs::write(disk_file(&dir, key), encode_disk(key, &entry).unwrap()).unwrap();
+        std::fs::write(dir.join("unknown"), b"garbage").unwrap();
+
+        let index = Mutex::new(Lru::new(1024));
+        load_disk_index(&dir, &index);
+        assert!(index.lock().unwrap().get(key).is_some());
+        assert!(!dir.join("unknown").exists());
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+}
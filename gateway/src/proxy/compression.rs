@@ -0,0 +1,248 @@
+use std::pin::Pin;
+
+use async_compression::{
+    Level,
+    tokio::bufread::{BrotliEncoder, GzipEncoder, ZstdEncoder},
+};
+use futures_util::TryStreamExt;
+use http_body::Body;
+use http_body_util::{BodyExt, StreamBody};
+use hyper::{
+    HeaderMap, Method, Response, StatusCode,
+    body::Frame,
+    header::{
+        ACCEPT_ENCODING, ACCEPT_RANGES, CACHE_CONTROL, CONTENT_ENCODING, CONTENT_LENGTH,
+        CONTENT_TYPE, ETAG, HeaderValue, VARY,
+    },
+};
+use shared::models::websites::{DatabaseWebsiteCompression, DatabaseWebsiteCompressionAlgorithm};
+use tokio::io::AsyncRead;
+use tokio_util::io::{ReaderStream, StreamReader};
+
+use crate::transport::CResponse;
+
+/// Content-Encoding 中的名称
+fn encoding_name(algorithm: DatabaseWebsiteCompressionAlgorithm) -> &'static str {
+    match algorithm {
+        DatabaseWebsiteCompressionAlgorithm::Zstd => "zstd",
+        DatabaseWebsiteCompressionAlgorithm::Brotli => "br",
+        DatabaseWebsiteCompressionAlgorithm::Gzip => "gzip",
+    }
+}
+
+/// 解析 Accept-Encoding 中的 q 值，缺省为 1
+fn quality(params: &str) -> f32 {
+    params
+        .split(';')
+        .filter_map(|v| v.trim().strip_prefix("q="))
+        .find_map(|v| v.trim().parse::<f32>().ok())
+        .unwrap_or(1.0)
+}
+
+#[derive(Debug)]
+pub struct Compression {
+    config: DatabaseWebsiteCompression,
+}
+
+impl Compression {
+    pub fn new(config: &DatabaseWebsiteCompression) -> Self {
+        Self {
+            config: config.clone(),
+        }
+    }
+
+    /// 选择客户端权重最高的算法，权重相同时按配置顺序
+    fn negotiate(&self, headers: &HeaderMap) -> Option<DatabaseWebsiteCompressionAlgorithm> {
+        let accepted = headers
+            .get_all(ACCEPT_ENCODING)
+            .iter()
+            .filter_map(|v| v.to_str().ok())
+            .flat_map(|v| v.split(','))
+            .map(|v| {
+                let (name, params) = v.split_once(';').unwrap_or((v, ""));
+                (name.trim().to_ascii_lowercase(), quality(params))
+            })
+            .collect::<Vec<_>>();
+        let mut best: Option<(DatabaseWebsiteCompressionAlgorithm, f32)> = None;
+        for algorithm in &self.config.algorithms {
+            let q = accepted
+                .iter()
+                .find(|(name, _)| name == encoding_name(*algorithm))
+                .or_else(|| accepted.iter().find(|(name, _)| name == "*"))
+                .map_or(0.0, |(_, q)| *q);
+            if q > 0.0 && best.is_none_or(|(_, b)| q > b) {
+                best = Some((*algorithm, q));
+            }
+        }
+        best.map(|(algorithm, _)| algorithm)
+    }
+
+    /// Content-Type 在允许列表中且长度不小于 min_size
+    fn is_compressible(&self, resp: &Response<CResponse>) -> bool {
+        let Some(content_type) = resp
+            .headers()
+            .get(CONTENT_TYPE)
+            .and_then(|v| v.to_str().ok())
+            .and_then(|v| v.split(';').next())
+            .map(|v| v.trim().to_ascii_lowercase())
+        else {
+            return false;
+        };
+        let allowed = self
+            .config
+            .mime_types
+            .iter()
+            .any(|v| match v.strip_suffix("/*") {
+                Some(prefix) => content_type
+                    .strip_prefix(prefix)
+                    .is_some_and(|v| v.starts_with('/')),
+                None => v.eq_ignore_ascii_case(&content_type),
+            });
+        let length = resp
+            .headers()
+            .get(CONTENT_LENGTH)
+            .and_then(|v| v.to_str().ok())
+            .and_then(|v| v.parse::<u64>().ok())
+            .or(resp.body().size_hint().exact());
+        allowed && length.is_none_or(|v| v >= self.config.min_size)
+    }
+
+    pub fn apply(
+        &self,
+        method: &Method,
+        req_headers: &HeaderMap,
+        mut resp: Response<CResponse>,
+    ) -> Response<CResponse> {
+        let status = resp.status();
+        if !self.config.enabled
+            || *method == Method::HEAD
+            || status.is_informational()
+            || matches!(
+                status,
+                StatusCode::NO_CONTENT | StatusCode::NOT_MODIFIED | StatusCode::PARTIAL_CONTENT
+            )
+            || resp.headers().contains_key(CONTENT_ENCODING)
+            || resp
+                .headers()
+                .get_all(CACHE_CONTROL)
+                .iter()
+                .filter_map(|v| v.to_str().ok())
+                .any(|v| v.to_ascii_lowercase().contains("no-transform"))
+            || !self.is_compressible(&resp)
+        {
+            return resp;
+        }
+        // 响应会因 Accept-Encoding 不同而不同，即使这次没有压缩
+        let headers = resp.headers_mut();
+        let has_vary = headers
+            .get_all(VARY)
+            .iter()
+            .filter_map(|v| v.to_str().ok())
+            .flat_map(|v| v.split(','))
+            .any(|v| {
+                let v = v.trim();
+                v == "*" || v.eq_ignore_ascii_case("accept-encoding")
+            });
+        if !has_vary {
+            headers.append(VARY, HeaderValue::from_static("Accept-Encoding"));
+        }
+        let Some(algorithm) = self.negotiate(req_headers) else {
+            return resp;
+        };
+
+        headers.remove(CONTENT_LENGTH);
+        headers.remove(ACCEPT_RANGES);
+        headers.insert(
+            CONTENT_ENCODING,
+            HeaderValue::from_static(encoding_name(algorithm)),
+        );
+        // 压缩后的内容与原始内容不再逐字节相同，强 ETag 改为弱 ETag
+        if let Some(etag) = headers.get(ETAG).and_then(|v| v.to_str().ok())
+            && !etag.starts_with("W/")
+            && let Ok(weak) = HeaderValue::from_str(&format!("W/{etag}"))
+        {
+            headers.insert(ETAG, weak);
+        }
+        let (parts, body) = resp.into_parts();
+        Response::from_parts(parts, self.compress(body, algorithm))
+    }
+
+    fn compress(
+        &self,
+        body: CResponse,
+        algorithm: DatabaseWebsiteCompressionAlgorithm,
+    ) -> CResponse {
+        let reader = StreamReader::new(body.into_data_stream().map_err(std::io::Error::other));
+        let encoder: Pin<Box<dyn AsyncRead + Send>> = match algorithm {
+            DatabaseWebsiteCompressionAlgorithm::Zstd => Box::pin(ZstdEncoder::with_quality(
+                reader,
+                Level::Precise(self.config.zstd_level),
+            )),
+            DatabaseWebsiteCompressionAlgorithm::Brotli => Box::pin(BrotliEncoder::with_quality(
+                reader,
+                Level::Precise(self.config.brotli_level),
+            )),
+            DatabaseWebsiteCompressionAlgorithm::Gzip => Box::pin(GzipEncoder::with_quality(
+                reader,
+                Level::Precise(self.config.gzip_level),
+            )),
+        };
+        let stream = ReaderStream::new(encoder)
+            .map_ok(Frame::data)
+            .map_err(|e| -> Box<dyn std::error::Error + Send + Sync> { e.into() });
+        CResponse::Stream(StreamBody::new(stream).boxed_unsync())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use DatabaseWebsiteCompressionAlgorithm::{Brotli, Gzip, Zstd};
+
+    fn negotiate(
+        algorithms: &[DatabaseWebsiteCompressionAlgorithm],
+        accept: &str,
+    ) -> Option<DatabaseWebsiteCompressionAlgorithm> {
+        let compression = Compression::new(&DatabaseWebsiteCompression {
+            enabled: true,
+            algorithms: algorithms.to_vec(),
+            ..Default::default()
+        });
+        let mut headers = HeaderMap::new();
+        headers.insert(ACCEPT_ENCODING, HeaderValue::from_str(accept).unwrap());
+        compression.negotiate(&headers)
+    }
+
+    #[test]
+    fn prefers_configured_order_on_equal_quality() {
+        assert_eq!(
+            negotiate(&[Zstd, Brotli, Gzip], "gzip, br, zstd"),
+            Some(Zstd)
+        );
+        assert_eq!(negotiate(&[Gzip, Brotli], "br, gzip"), Some(Gzip));
+    }
+
+    #[test]
+    fn prefers_higher_quality() {
+        assert_eq!(
+            negotiate(&[Zstd, Brotli, Gzip], "zstd;q=0.5, GZIP;q=0.8"),
+            Some(Gzip)
+        );
+    }
+
+    #[test]
+    fn honours_wildcard_and_zero_quality() {
+        assert_eq!(negotiate(&[Brotli, Gzip], "*"), Some(Brotli));
+        assert_eq!(negotiate(&[Brotli, Gzip], "br;q=0, *;q=0.1"), Some(Gzip));
+        assert_eq!(negotiate(&[Brotli, Gzip], "identity"), None);
+        assert_eq!(negotiate(&[Gzip], "gzip;q=0"), None);
+    }
+
+    #[test]
+    fn parses_quality() {
+        assert_eq!(quality(""), 1.0);
+        assert_eq!(quality(" q=0.3"), 0.3);
+        assert_eq!(quality("level=1; q=0"), 0.0);
+        assert_eq!(quality("q=abc"), 1.0);
+    }
+}
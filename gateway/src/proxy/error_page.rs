@@ -0,0 +1,148 @@
+use hyper::{
+    HeaderMap, Response, StatusCode,
+    header::{ACCEPT, CACHE_CONTROL, CONTENT_TYPE},
+};
+use shared::{models::websites::DatabaseWebsiteErrorPages, objectid::ObjectId};
+
+use crate::transport::CResponse;
+
+const DEFAULT_TEMPLATE: &str = include_str!("../../../assets/error_pages/template.html");
+const CHALLENGE_TEMPLATE: &str = include_str!("../../../assets/error_pages/challenge.html");
+
+fn message(status: StatusCode) -> &'static str {
+    match status {
+        StatusCode::BAD_REQUEST => "The request could not be understood by the gateway.",
+        StatusCode::UNAUTHORIZED => "Authentication is required to access this resource.",
+        StatusCode::FORBIDDEN => "Access from your IP address is not allowed.",
+        StatusCode::NOT_FOUND => "The requested resource could not be found.",
+        StatusCode::TOO_MANY_REQUESTS => "Too many requests, please retry later.",
+        StatusCode::GATEWAY_TIMEOUT => "The backend did not respond in time.",
+        _ => "The gateway failed to process this request.",
+    }
+}
+
+fn escape_html(value: &str) -> String {
+    value
+        .replace('&', "&amp;")
+        .replace('<', "&lt;")
+        .replace('>', "&gt;")
+        .replace('"', "&quot;")
+}
+
+/// 替换模板中的 `${{ name }}`，未知的变量保持原样
+fn render_template(template: &str, vars: &[(&str, &str)]) -> String {
+    let mut result = String::with_capacity(template.len());
+    let mut rest = template;
+    while let Some(start) = rest.find("${{") {
+        let Some(end) = rest[start..].find("}}") else {
+            break;
+        };
+        result.push_str(&rest[..start]);
+        let name = rest[start + 3..start + end].trim();
+        match vars.iter().find(|(k, _)| *k == name) {
+            Some((_, value)) => result.push_str(&escape_html(value)),
+            None => result.push_str(&rest[start..start + end + 2]),
+        }
+        rest = &rest[start + end + 2..];
+    }
+    result.push_str(rest);
+    result
+}
+
+/// Accept 中 JSON 排在 HTML 之前（或没有 HTML）时返回 JSON
+pub fn prefers_json(headers: &HeaderMap) -> bool {
+    let accept = headers
+        .get_all(ACCEPT)
+        .iter()
+        .filter_map(|v| v.to_str().ok())
+        .flat_map(|v| v.split(','))
+        .map(|v| v.split(';').next().unwrap_or_default().trim())
+        .collect::<Vec<_>>();
+    let json = accept
+        .iter()
+        .position(|v| *v == "application/json" || v.ends_with("+json"));
+    let html = accept.iter().position(|v| *v == "text/html" || *v == "*/*");
+    match (json, html) {
+        (Some(json), Some(html)) => json < html,
+        (Some(_), None) => true,
+        _ => false,
+    }
+}
+
+/// 生成网关自身的错误响应，网站配置了页面时优先使用
+pub fn error_response(
+    status: StatusCode,
+    req_id: &ObjectId,
+    json: bool,
+    pages: Option<&DatabaseWebsiteErrorPages>,
+) -> Response<CResponse> {
+    let title = status.canonical_reason().unwrap_or("Error");
+    let message = message(status);
+    let req_id = req_id.to_string();
+    let (content_type, body) = match json {
+        true => (
+            "application/json",
+            serde_json::json!({
+                "status": status.as_u16(),
+                "error": title,
+                "message": message,
+                "request_id": req_id,
+            })
+            .to_string(),
+        ),
+        false => {
+            let template = pages
+                .and_then(|v| v.pages.get(&status.as_u16()).or(v.template.as_ref()))
+                .map_or(DEFAULT_TEMPLATE, |v| v.as_str());
+            (
+                "text/html; charset=utf-8",
+                render_template(
+                    template,
+                    &[
+                        ("status", status.as_str()),
+                        ("title", title),
+                        ("message", message),
+                        ("request_id", &req_id),
+                    ],
+                ),
+            )
+        }
+    };
+    Response::builder()
+        .status(status)
+        .header(CONTENT_TYPE, content_type)
+        .header(CACHE_CONTROL, "no-store")
+        .header("X-Request-Id", req_id.as_str())
+        .body(CResponse::new_from_string(body))
+        .unwrap()
+}
+
+/// JS 工作量证明页面，浏览器算出 nonce 写入 cookie 后刷新重新请求
+pub fn challenge_response(
+    req_id: &ObjectId,
+    cookie: &str,
+    challenge: &str,
+    difficulty: u32,
+    max_age: u64,
+) -> Response<CResponse> {
+    let req_id = req_id.to_string();
+    let body = render_template(
+        CHALLENGE_TEMPLATE,
+        &[
+            ("title", "Checking your browser"),
+            ("message", "Please wait while we verify your request."),
+            ("request_id", &req_id),
+            ("cookie", cookie),
+            ("challenge", challenge),
+            ("difficulty", &difficulty.to_string()),
+            ("max_age", &max_age.to_string()),
+        ],
+    );
+    Response::builder()
+        .status(StatusCode::FORBIDDEN)
+        .header(CONTENT_TYPE, "text/html; charset=utf-8")
+        .header(CACHE_CONTROL, "no-store")
+        .header("X-Request-Id", req_id.as_str())
+        .body(CResponse::new_from_string(body))
+        .unwrap()
+}
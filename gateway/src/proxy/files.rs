@@ -0,0 +1,448 @@
+use std::{
+    io::SeekFrom,
+    path::{Path, PathBuf},
+    time::{SystemTime, UNIX_EPOCH},
+};
+
+use futures_util::TryStreamExt;
+use http_body_util::{BodyExt, StreamBody};
+use hyper::{
+    HeaderMap, Method, Response, StatusCode, Uri,
+    body::Frame,
+    header::{
+        ACCEPT_ENCODING, ACCEPT_RANGES, ALLOW, CONTENT_ENCODING, CONTENT_LENGTH, CONTENT_RANGE,
+        CONTENT_TYPE, ETAG, IF_MODIFIED_SINCE, IF_NONE_MATCH, IF_RANGE, LAST_MODIFIED, LOCATION,
+        RANGE, VARY,
+    },
+    http::response::Builder,
+};
+use percent_encoding::{AsciiSet, CONTROLS, percent_decode_str, utf8_percent_encode};
+use shared::models::websites::{DatabaseWebsiteBackend, DatabaseWebsiteStaticFiles};
+use tokio::io::{AsyncReadExt, AsyncSeekExt};
+use tokio_util::io::ReaderStream;
+
+use crate::transport::CResponse;
+
+// 目录列表中链接需要转义的字符
+const PATH_SEGMENT: &AsciiSet = &CONTROLS
+    .add(b' ')
+    .add(b'"')
+    .add(b'#')
+    .add(b'%')
+    .add(b'<')
+    .add(b'>')
+    .add(b'?')
+    .add(b'`')
+    .add(b'{')
+    .add(b'}')
+    .add(b'/');
+
+// 按优先级排列的预压缩文件
+const PRECOMPRESSED: [(&str, &str); 2] = [("br", ".br"), ("gzip", ".gz")];
+
+/// 客户端接受的编码，忽略 q=0
+fn accepts_encoding(headers: &HeaderMap, encoding: &str) -> bool {
+    headers
+        .get_all(ACCEPT_ENCODING)
+        .iter()
+        .filter_map(|v| v.to_str().ok())
+        .flat_map(|v| v.split(','))
+        .any(|v| {
+            let mut params = v.split(';').map(|v| v.trim());
+            params
+                .next()
+                .is_some_and(|name| name.eq_ignore_ascii_case(encoding) || name == "*")
+                && params.all(|v| !matches!(v, "q=0" | "q=0.0" | "q=0.00" | "q=0.000"))
+        })
+}
+
+/// 解析单个 `bytes=` 范围，多个范围时返回 None（返回完整文件）。
+/// 范围不合法时返回 Err，响应 416
+fn parse_range(value: &str, len: u64) -> Result<Option<(u64, u64)>, ()> {
+    let Some(spec) = value.trim().strip_prefix("bytes=") else {
+        return Ok(None);
+    };
+    if spec.contains(',') {
+        return Ok(None);
+    }
+    let (start, end) = spec.trim().split_once('-').ok_or(())?;
+    let (start, end) = match (start.trim(), end.trim()) {
+        ("", suffix) => {
+            let suffix = suffix.parse::<u64>().map_err(|_| ())?;
+            if suffix == 0 {
+                return Err(());
+            }
+            (len.saturating_sub(suffix), len.saturating_sub(1))
+        }
+        (start, "") => (start.parse::<u64>().map_err(|_| ())?, len.saturating_sub(1)),
+        (start, end) => (
+            start.parse::<u64>().map_err(|_| ())?,
+            end.parse::<u64>()
+                .map_err(|_| ())?
+                .min(len.saturating_sub(1)),
+        ),
+    };
+    if start > end || start >= len {
+        return Err(());
+    }
+    Ok(Some((start, end)))
+}
+
+fn escape_html(value: &str) -> String {
+    value
+        .replace('&', "&amp;")
+        .replace('<', "&lt;")
+        .replace('>', "&gt;")
+        .replace('"', "&quot;")
+}
+
+// ---------- 单个文件的元数据 ----------
+struct FileMeta {
+    path: PathBuf,
+    len: u64,
+    modified: SystemTime,
+    encoding: Option<&'static str>,
+}
+
+impl FileMeta {
+    fn etag(&self) -> String {
+        let modified = self
+            .modified
+            .duration_since(UNIX_EPOCH)
+            .map_or(0, |v| v.as_secs());
+        match self.encoding {
+            Some(encoding) => format!("\"{modified:x}-{:x}-{encoding}\"", self.len),
+            None => format!("\"{modified:x}-{:x}\"", self.len),
+        }
+    }
+
+    /// If-None-Match 优先于 If-Modified-Since
+    fn is_not_modified(&self, headers: &HeaderMap, etag: &str) -> bool {
+        if let Some(value) = headers.get(IF_NONE_MATCH).and_then(|v| v.to_str().ok()) {
+            return value
+                .split(',')
+                .map(|v| v.trim().trim_start_matches("W/"))
+                .any(|v| v == "*" || v == etag);
+        }
+        headers
+            .get(IF_MODIFIED_SINCE)
+            .and_then(|v| v.to_str().ok())
+            .and_then(|v| httpdate::parse_http_date(v).ok())
+            .is_some_and(|since| {
+                httpdate::HttpDate::from(self.modified) <= httpdate::HttpDate::from(since)
+            })
+    }
+
+    /// 没有 If-Range 或者它与当前文件一致时才按 Range 返回
+    fn is_range_fresh(&self, headers: &HeaderMap, etag: &str) -> bool {
+        match headers.get(IF_RANGE).and_then(|v| v.to_str().ok()) {
+            None => true,
+            Some(value) if value.starts_with('"') => value == etag,
+            Some(value) => httpdate::parse_http_date(value).is_ok_and(|v| {
+                httpdate::HttpDate::from(v) == httpdate::HttpDate::from(self.modified)
+            }),
+        }
+    }
+}
+
+// ---------- file:// 后端 ----------
+#[derive(Debug)]
+pub struct StaticFiles {
+    /// 已解析符号链接的根目录
+    root: PathBuf,
+    config: DatabaseWebsiteStaticFiles,
+}
+
+impl StaticFiles {
+    pub fn new(backend: &DatabaseWebsiteBackend) -> anyhow::Result<Self> {
+        let root = backend
+            .url
+            .to_file_path()
+            .map_err(|_| anyhow::anyhow!("Invalid file backend: {}", backend.url))?;
+        let root = std::fs::canonicalize(&root)
+            .map_err(|e| anyhow::anyhow!("Invalid file backend {}: {e}", backend.url))?;
+        if !root.is_dir() {
+            return Err(anyhow::anyhow!(
+                "File backend is not a directory: {}",
+                backend.url
+            ));
+        }
+        Ok(Self {
+            root,
+            config: backend.files.clone(),
+        })
+    }
+
+    /// path 为改写后的路径，uri 为客户端原始请求（用于目录跳转）。
+    /// 路径不合法或文件不存在时返回 Err(状态码)，由调用方生成错误页
+    pub async fn serve(
+        &self,
+        method: &Method,
+        path: &str,
+        uri: &Uri,
+        headers: &HeaderMap,
+    ) -> anyhow::Result<Result<Response<CResponse>, StatusCode>> {
+        if method != Method::GET && method != Method::HEAD {
+            return Ok(Ok(Response::builder()
+                .status(StatusCode::METHOD_NOT_ALLOWED)
+                .header(ALLOW, "GET, HEAD")
+                .body(CResponse::new_from_string("Method Not Allowed"))?));
+        }
+        let Ok(decoded) = percent_decode_str(path).decode_utf8() else {
+            return Ok(Err(StatusCode::BAD_REQUEST));
+        };
+        // 不允许 `..` 等跳出根目录的路径
+        let mut path = self.root.clone();
+        for segment in decoded.split('/') {
+            match segment {
+                "" | "." => continue,
+                ".." => return Ok(Err(StatusCode::BAD_REQUEST)),
+                v if v.contains(['\\', '\0']) => return Ok(Err(StatusCode::BAD_REQUEST)),
+                v => path.push(v),
+            }
+        }
+
+        match self.resolve(&path).await {
+            Some(metadata) if metadata.is_dir() => {
+                if !uri.path().ends_with('/') {
+                    let location = match uri.query() {
+                        Some(query) => format!("{}/?{query}", uri.path()),
+                        None => format!("{}/", uri.path()),
+                    };
+                    return Ok(Ok(Response::builder()
+                        .status(StatusCode::MOVED_PERMANENTLY)
+                        .header(LOCATION, location)
+                        .body(CResponse::new_from_string(""))?));
+                }
+                for index in &self.config.index {
+                    let index = path.join(index);
+                    if self.resolve(&index).await.is_some_and(|v| v.is_file()) {
+                        return self.serve_file(&index, method, headers).await.map(Ok);
+                    }
+                }
+                if self.config.listing {
+                    return self.listing(&path, &decoded, method).await.map(Ok);
+                }
+            }
+            Some(metadata) if metadata.is_file() => {
+                return self.serve_file(&path, method, headers).await.map(Ok);
+            }
+            _ => {}
+        }
+        if self.config.spa_fallback {
+            let index = self.root.join("index.html");
+            if self.resolve(&index).await.is_some_and(|v| v.is_file()) {
+                return self.serve_file(&index, method, headers).await.map(Ok);
+            }
+        }
+        Ok(Err(StatusCode::NOT_FOUND))
+    }
+
+    /// 文件存在且（解析符号链接后）仍在根目录下时返回元数据
+    async fn resolve(&self, path: &Path) -> Option<std::fs::Metadata> {
+        let path = tokio::fs::canonicalize(path).await.ok()?;
+        if !path.starts_with(&self.root) {
+            return None;
+        }
+        tokio::fs::metadata(path).await.ok()
+    }
+
+    /// 客户端支持时选择预压缩文件
+    async fn select_variant(&self, path: &Path, headers: &HeaderMap) -> anyhow::Result<FileMeta> {
+        if self.config.precompressed {
+            for (encoding, extension) in PRECOMPRESSED {
+                if !accepts_encoding(headers, encoding) {
+                    continue;
+                }
+                let mut variant = path.as_os_str().to_owned();
+                variant.push(extension);
+                let variant = PathBuf::from(variant);
+                if let Some(metadata) = self.resolve(&variant).await
+                    && metadata.is_file()
+                {
+                    return Ok(FileMeta {
+                        path: variant,
+                        len: metadata.len(),
+                        modified: metadata.modified()?,
+                        encoding: Some(encoding),
+                    });
+                }
+            }
+        }
+        let metadata = tokio::fs::metadata(path).await?;
+        Ok(FileMeta {
+            path: path.to_path_buf(),
+            len: metadata.len(),
+            modified: metadata.modified()?,
+            encoding: None,
+        })
+    }
+
+    async fn serve_file(
+        &self,
+        path: &Path,
+        method: &Method,
+        headers: &HeaderMap,
+    ) -> anyhow::Result<Response<CResponse>> {
+        let file = self.select_variant(path, headers).await?;
+        let etag = file.etag();
+        let mut builder = Response::builder()
+            .header(ETAG, &etag)
+            .header(LAST_MODIFIED, httpdate::fmt_http_date(file.modified))
+            .header(ACCEPT_RANGES, "bytes");
+        if self.config.precompressed {
+            builder = builder.header(VARY, "Accept-Encoding");
+        }
+        if file.is_not_modified(headers, &etag) {
+            return Ok(builder
+                .status(StatusCode::NOT_MODIFIED)
+                .body(CResponse::new_from_string(""))?);
+        }
+        builder = builder.header(
+            CONTENT_TYPE,
+            mime_guess::from_path(path)
+                .first_or_octet_stream()
+                .essence_str(),
+        );
+        if let Some(encoding) = file.encoding {
+            builder = builder.header(CONTENT_ENCODING, encoding);
+        }
+
+        let range = match headers.get(RANGE).and_then(|v| v.to_str().ok()) {
+            Some(value) if *method == Method::GET && file.is_range_fresh(headers, &etag) => {
+                match parse_range(value, file.len) {
+                    Ok(range) => range,
+                    Err(()) => {
+                        return Ok(builder
+                            .status(StatusCode::RANGE_NOT_SATISFIABLE)
+                            .header(CONTENT_RANGE, format!("bytes */{}", file.len))
+                            .body(CResponse::new_from_string(""))?);
+                    }
+                }
+            }
+            _ => None,
+        };
+        let (start, len) = match range {
+            Some((start, end)) => {
+                builder = builder
+                    .status(StatusCode::PARTIAL_CONTENT)
+                    .header(CONTENT_RANGE, format!("bytes {start}-{end}/{}", file.len));
+                (start, end - start + 1)
+            }
+            None => (0, file.len),
+        };
+        builder = builder.header(CONTENT_LENGTH, len);
+        if *method == Method::HEAD {
+            return Ok(builder.body(CResponse::new_from_string(""))?);
+        }
+        body_from_file(builder, &file.path, start, len).await
+    }
+
+    async fn listing(
+        &self,
+        path: &Path,
+        display: &str,
+        method: &Method,
+    ) -> anyhow::Result<Response<CResponse>> {
+        let mut entries = vec![];
+        let mut dir = tokio::fs::read_dir(path).await?;
+        while let Some(entry) = dir.next_entry().await? {
+            let name = entry.file_name().to_string_lossy().to_string();
+            let is_dir = entry.file_type().await?.is_dir();
+            entries.push((is_dir, name));
+        }
+        // 目录在前，再按名称排序
+        entries.sort_by(|a, b| b.0.cmp(&a.0).then_with(|| a.1.cmp(&b.1)));
+        let title = escape_html(display);
+        let mut html = format!(
+            "<!DOCTYPE html>\n<html><head><meta charset=\"utf-8\"><title>Index of {title}</title></head>\n<body><h1>Index of {title}</h1><ul>\n"
+        );
+        if display != "/" {
+            html.push_str("<li><a href=\"../\">../</a></li>\n");
+        }
+        for (is_dir, name) in entries {
+            let suffix = if is_dir { "/" } else { "" };
+            html.push_str(&format!(
+                "<li><a href=\"{}{suffix}\">{}{suffix}</a></li>\n",
+                utf8_percent_encode(&name, PATH_SEGMENT),
+                escape_html(&name)
+            ));
+        }
+        html.push_str("</ul></body></html>\n");
+        let builder = Response::builder()
+            .header(CONTENT_TYPE, "text/html; charset=utf-8")
+            .header(CONTENT_LENGTH, html.len());
+        Ok(builder.body(match *method == Method::HEAD {
+            true => CResponse::new_from_string(""),
+            false => CResponse::new_from_string(html),
+        })?)
+    }
+}
+
+async fn body_from_file(
+    builder: Builder,
+    path: &Path,
+    start: u64,
+    len: u64,
+) -> anyhow::Result<Response<CResponse>> {
+    let mut file = tokio::fs::File::open(path).await?;
+    if start > 0 {
+        file.seek(SeekFrom::Start(start)).await?;
+    }
+    let stream = ReaderStream::new(file.take(len))
+        .map_ok(Frame::data)
+        .map_err(|e| -> Box<dyn std::error::Error + Send + Sync> { e.into() });
+    Ok(builder.body(CResponse::Stream(StreamBody::new(stream).boxed_unsync()))?)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parses_byte_ranges() {
+        assert_eq!(parse_range("bytes=0-99", 1000), Ok(Some((0, 99))));
+        assert_eq!(parse_range("bytes=500-", 1000), Ok(Some((500, 999))));
+        assert_eq!(parse_range("bytes=-100", 1000), Ok(Some((900, 999))));
+        assert_eq!(parse_range("bytes=-2000", 1000), Ok(Some((0, 999))));
+        assert_eq!(parse_range("bytes=900-2000", 1000), Ok(Some((900, 999))));
+    }
+
+    #[test]
+    fn ignores_unsupported_ranges() {
+        assert_eq!(parse_range("items=0-1", 1000), Ok(None));
+        assert_eq!(parse_range("bytes=0-1,5-6", 1000), Ok(None));
+    }
+
+    #[test]
+    fn rejects_unsatisfiable_ranges() {
+        assert_eq!(parse_range("bytes=1000-", 1000), Err(()));
+        assert_eq!(parse_range("bytes=5-1", 1000), Err(()));
+        assert_eq!(parse_range("bytes=-0", 1000), Err(()));
+        assert_eq!(parse_range("bytes=a-b", 1000), Err(()));
+        assert_eq!(parse_range("bytes=0-0", 0), Err(()));
+    }
+
+    fn backend(url: &str) -> DatabaseWebsiteBackend {
+        DatabaseWebsiteBackend {
+            url: url.parse().unwrap(),
+            balance: 1,
+            main: true,
+            host: None,
+            tls: Default::default(),
+            protocol: Default::default(),
+            files: Default::default(),
+        }
+    }
+
+    #[test]
+    fn canonicalizes_root_on_load() {
+        let dir = std::env::temp_dir().join(format!("files-{}", std::process::id()));
+        std::fs::create_dir_all(dir.join("a")).unwrap();
+        let url = format!("file://{}/a/..", dir.display());
+        let files = StaticFiles::new(&backend(&url)).unwrap();
+        assert_eq!(files.root, std::fs::canonicalize(&dir).unwrap());
+        std::fs::remove_dir_all(&dir).unwrap();
+        assert!(StaticFiles::new(&backend(&url)).is_err());
+    }
+}
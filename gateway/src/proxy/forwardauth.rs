@@ -0,0 +1,154 @@
+use std::time::Duration;
+
+use bytes::Bytes;
+use http_body_util::{BodyExt, Empty};
+use hyper::{HeaderMap, Method, Request, Response, StatusCode, Uri, header::HeaderName};
+use shared::models::websites::{
+    DatabaseWebsiteBackend, DatabaseWebsiteBackendProtocol, DatabaseWebsiteConfig,
+    DatabaseWebsiteForwardAuth, DatabaseWebsiteStaticFiles,
+};
+use tokio::time::{Instant, timeout};
+use url::Position;
+
+use crate::{
+    proxy::group::WebSiteBackend,
+    state::ClientState,
+    transport::{CRequest, CResponse},
+};
+
+pub enum ForwardAuthResult {
+    /// 认证通过，需要复制到上游请求的头部
+    Allow(HeaderMap),
+    /// 认证服务返回的 3xx / 401 / 403，原样返回给客户端（例如跳转到登录页）
+    Deny(Response<CResponse>),
+}
+
+// ---------- 转发前的认证子请求 ----------
+#[derive(Debug)]
+pub struct ForwardAuth {
+    /// 复用后端的连接池、DNS 刷新和 TLS 配置
+    backend: WebSiteBackend,
+    request_headers: Vec<HeaderName>,
+    response_headers: Vec<HeaderName>,
+    timeout: Duration,
+}
+
+impl ForwardAuth {
+    pub async fn new(
+        config: &DatabaseWebsiteForwardAuth,
+        website_config: &DatabaseWebsiteConfig,
+    ) -> anyhow::Result<Self> {
+        let backend = WebSiteBackend::new(
+            DatabaseWebsiteBackend {
+                url: config.url.clone(),
+                balance: 1,
+                main: true,
+                host: None,
+                tls: config.tls.clone(),
+                protocol: DatabaseWebsiteBackendProtocol::default(),
+                files: DatabaseWebsiteStaticFiles::default(),
+            },
+            website_config,
+        )
+        .await?;
+        let parse = |names: &[String]| {
+            names
+                .iter()
+                .map(|v| HeaderName::from_bytes(v.as_bytes()))
+                .collect::<Result<Vec<_>, _>>()
+        };
+        Ok(Self {
+            backend,
+            request_headers: parse(&config.request_headers)?,
+            response_headers: parse(&config.response_headers)?,
+            timeout: Duration::from_secs(config.timeout.max(1)),
+        })
+    }
+
+    fn build_request(
+        &self,
+        state: &ClientState,
+        method: &Method,
+        uri: &Uri,
+        headers: &HeaderMap,
+    ) -> anyhow::Result<Request<CRequest>> {
+        let url = &self.backend.inner().url;
+        let mut req = Request::get(&url[Position::BeforePath..]);
+        let req_headers = req.headers_mut().unwrap();
+        for name in &self.request_headers {
+            for value in headers.get_all(name) {
+                req_headers.append(name, value.clone());
+            }
+        }
+        req_headers.insert(
+            "Host",
+            url[Position::BeforeHost..Position::AfterPort].parse()?,
+        );
+        req_headers.insert("X-Forwarded-Method", method.as_str().parse()?);
+        req_headers.insert(
+            "X-Forwarded-Uri",
+            uri.path_and_query().map_or("/", |v| v.as_str()).parse()?,
+        );
+        req_headers.insert("X-Forwarded-Host", state.host.parse()?);
+        req_headers.insert("X-Forwarded-Proto", state.scheme().parse()?);
+        req_headers.insert("X-Forwarded-For", state.remote_addr().to_string().parse()?);
+        req_headers.insert("X-Real-Ip", state.remote_addr().to_string().parse()?);
+        Ok(req.body(Empty::<Bytes>::new().map_err(|e| e.into()).boxed_unsync())?)
+    }
+
+    /// 认证服务返回 2xx、3xx、401、403 以外的状态时视为网关错误
+    pub async fn check(
+        &self,
+        state: &ClientState,
+        method: &Method,
+        uri: &Uri,
+        headers: &HeaderMap,
+        deadline: Option<Instant>,
+    ) -> anyhow::Result<ForwardAuthResult> {
+        let req = self.build_request(state, method, uri, headers)?;
+        let resp = timeout(self.timeout, async {
+            let mut sender = self.backend.pool().get().await?;
+            sender.send_request(req).await
+        })
+        .await??;
+        match resp.status() {
+            status if status.is_success() => {
+                let mut granted = HeaderMap::new();
+                for name in &self.response_headers {
+                    for value in resp.headers().get_all(name) {
+                        granted.append(name, value.clone());
+                    }
+                }
+                // 读完并丢弃 body，HTTP/1.1 连接才能放回连接池
+                let mut body = resp.into_body();
+                let _ = timeout(self.timeout, async {
+                    while let Some(Ok(_)) = body.frame().await {}
+                })
+                .await;
+                Ok(ForwardAuthResult::Allow(granted))
+            }
+            status
+                if status.is_redirection()
+                    || status == StatusCode::UNAUTHORIZED
+                    || status == StatusCode::FORBIDDEN =>
+            {
+                Ok(ForwardAuthResult::Deny(super::wrap_incoming(
+                    state, resp, deadline, None,
+                )))
+            }
+            status => Err(anyhow::anyhow!(
+                "Forward auth service returned unexpected status {status}"
+            )),
+        }
+    }
+
+    /// 删除客户端伪造的同名头部后写入认证服务返回的头部
+    pub fn apply(&self, headers: &mut HeaderMap, granted: HeaderMap) {
+        for name in &self.response_headers {
+            headers.remove(name);
+        }
+        for (name, value) in granted.iter() {
+            headers.append(name, value.clone());
+        }
+    }
+}
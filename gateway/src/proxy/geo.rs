@@ -0,0 +1,219 @@
+use std::{
+    net::IpAddr,
+    sync::{LazyLock, RwLock},
+};
+
+use geoip::LookupResult;
+use hmac::{Hmac, Mac};
+use hyper::{HeaderMap, Response, StatusCode, header::COOKIE};
+use sha2::{Digest, Sha256};
+use shared::{
+    database::{
+        configuration::{DatabaseConfigurationModifyRepository, DatabaseConfigurationRepository},
+        get_database,
+    },
+    models::websites::{DatabaseWebsiteGeo, DatabaseWebsiteGeoAction},
+};
+
+use crate::{proxy::error_page, state::ClientState, transport::CResponse};
+
+const CHALLENGE_COOKIE: &str = "gateway_challenge";
+const CHALLENGE_SECRET_KEY: &str = "challenge_secret";
+/// 客户端需要找到的 SHA-256 前导零位数，浏览器中约需数十万次哈希
+const CHALLENGE_DIFFICULTY: u32 = 16;
+
+type HmacSha256 = Hmac<Sha256>;
+
+// 启动时替换为数据库中的密钥，使多个实例签发的 cookie 互相认可
+static CHALLENGE_SECRET: LazyLock<RwLock<Vec<u8>>> = LazyLock::new(|| RwLock::new(random_secret()));
+
+fn random_secret() -> Vec<u8> {
+    let mut bytes = [0u8; 32];
+    rand::fill(&mut bytes);
+    bytes.to_vec()
+}
+
+pub async fn load_challenge_secret() -> anyhow::Result<()> {
+    let database = get_database();
+    database
+        .set_configuration_if_absent(CHALLENGE_SECRET_KEY, hex::encode(random_secret()))
+        .await?;
+    let secret = database
+        .get_configuration::<String>(CHALLENGE_SECRET_KEY)
+        .await?
+        .ok_or_else(|| anyhow::anyhow!("Challenge secret is missing"))?;
+    *CHALLENGE_SECRET.write().unwrap() = hex::decode(secret)?;
+    Ok(())
+}
+
+fn now() -> i64 {
+    get_database()
+        .get_database_time()
+        .unwrap_or_else(|_| chrono::Utc::now())
+        .timestamp()
+}
+
+fn mac(ip: IpAddr, expires: i64) -> HmacSha256 {
+    let mut mac = HmacSha256::new_from_slice(&CHALLENGE_SECRET.read().unwrap())
+        .expect("HMAC accepts keys of any length");
+    mac.update(format!("{ip}|{expires}").as_bytes());
+    mac
+}
+
+/// 下发给页面的题目，格式为 `{过期时间}.{签名}`，签名绑定客户端 IP
+fn sign(ip: IpAddr, expires: i64) -> String {
+    format!(
+        "{expires}.{}",
+        hex::encode(mac(ip, expires).finalize().into_bytes())
+    )
+}
+
+fn verify_challenge(ip: IpAddr, challenge: &str) -> bool {
+    let Some((expires, signature)) = challenge.split_once('.') else {
+        return false;
+    };
+    let (Ok(expires), Ok(signature)) = (expires.parse::<i64>(), hex::decode(signature)) else {
+        return false;
+    };
+    expires >= now() && mac(ip, expires).verify_slice(&signature).is_ok()
+}
+
+fn leading_zero_bits(hash: &[u8]) -> u32 {
+    let mut count = 0;
+    for byte in hash {
+        count += byte.leading_zeros();
+        if *byte != 0 {
+            break;
+        }
+    }
+    count
+}
+
+/// cookie 的格式为 `{题目}.{nonce}`，整体的 SHA-256 需要满足难度
+fn verify(ip: IpAddr, token: &str) -> bool {
+    let Some((challenge, nonce)) = token.rsplit_once('.') else {
+        return false;
+    };
+    !nonce.is_empty()
+        && nonce.len() <= 20
+        && nonce.bytes().all(|v| v.is_ascii_digit())
+        && leading_zero_bits(&Sha256::digest(token)) >= CHALLENGE_DIFFICULTY
+        && verify_challenge(ip, challenge)
+}
+
+fn challenge_cookies(headers: &HeaderMap) -> impl Iterator<Item = &str> {
+    headers
+        .get_all(COOKIE)
+        .iter()
+        .filter_map(|v| v.to_str().ok())
+        .flat_map(|v| v.split(';'))
+        .filter_map(|v| v.trim().split_once('='))
+        .filter(|(name, _)| *name == CHALLENGE_COOKIE)
+        .map(|(_, value)| value)
+}
+
+// ---------- 国家和地区匹配 ----------
+#[derive(Debug)]
+pub struct GeoMatcher {
+    countries: Vec<String>,
+    /// 国家代码加地区代码，如 CN-GD
+    regions: Vec<String>,
+}
+
+impl GeoMatcher {
+    pub fn new(countries: &[String], regions: &[String]) -> Self {
+        Self {
+            countries: countries.iter().map(|v| v.to_ascii_uppercase()).collect(),
+            regions: regions.iter().map(|v| v.to_ascii_uppercase()).collect(),
+        }
+    }
+
+    /// 无法解析位置时不匹配
+    pub fn is_match(&self, location: Option<&LookupResult>) -> bool {
+        let Some(country) = location.and_then(|v| v.country.as_deref()) else {
+            return false;
+        };
+        let country = country.to_ascii_uppercase();
+        self.countries.contains(&country)
+            || location.and_then(|v| v.city.as_deref()).is_some_and(|v| {
+                self.regions
+                    .contains(&format!("{country}-{}", v.to_ascii_uppercase()))
+            })
+    }
+}
+
+// ---------- 网站的地区规则 ----------
+#[derive(Debug)]
+pub struct GeoRules {
+    rules: Vec<(GeoMatcher, DatabaseWebsiteGeoAction)>,
+    default: DatabaseWebsiteGeoAction,
+    challenge_ttl: u64,
+}
+
+impl GeoRules {
+    pub fn new(config: &DatabaseWebsiteGeo) -> Self {
+        Self {
+            rules: config
+                .rules
+                .iter()
+                .map(|v| (GeoMatcher::new(&v.countries, &v.regions), v.action))
+                .collect(),
+            default: config.default,
+            challenge_ttl: config.challenge_ttl,
+        }
+    }
+
+    fn action(&self, location: Option<&LookupResult>) -> DatabaseWebsiteGeoAction {
+        self.rules
+            .iter()
+            .find(|(matcher, _)| matcher.is_match(location))
+            .map_or(self.default, |(_, action)| *action)
+    }
+
+    /// 拒绝或需要验证时返回响应，放行时返回 None
+    pub fn check(&self, state: &ClientState, headers: &HeaderMap) -> Option<Response<CResponse>> {
+        match self.action(state.base.location()) {
+            DatabaseWebsiteGeoAction::Allow => None,
+            DatabaseWebsiteGeoAction::Deny => Some(error_page::error_response(
+                StatusCode::FORBIDDEN,
+                &state.id,
+                error_page::prefers_json(headers),
+                Some(&state.website.inner().config.error_pages),
+            )),
+            DatabaseWebsiteGeoAction::Challenge => {
+                let ip = state.remote_addr();
+                if challenge_cookies(headers).any(|v| verify(ip, v)) {
+                    return None;
+                }
+                let challenge = sign(ip, now() + self.challenge_ttl as i64);
+                Some(error_page::challenge_response(
+                    &state.id,
+                    CHALLENGE_COOKIE,
+                    &challenge,
+                    CHALLENGE_DIFFICULTY,
+                    self.challenge_ttl,
+                ))
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn counts_leading_zero_bits() {
+        assert_eq!(leading_zero_bits(&[0xff, 0x00]), 0);
+        assert_eq!(leading_zero_bits(&[0x00, 0x01, 0xff]), 15);
+        assert_eq!(leading_zero_bits(&[0x00, 0x00]), 16);
+    }
+
+    #[test]
+    fn rejects_tokens_without_work() {
+        let ip = IpAddr::from([127, 0, 0, 1]);
+        assert!(!verify(ip, "1700000000.abcdef"));
+        assert!(!verify(ip, "1700000000.abcdef."));
+        assert!(!verify(ip, "1700000000.abcdef.x1"));
+    }
+}
@@ -0,0 +1,172 @@
+use std::{
+    sync::{Arc, Mutex},
+    time::Duration,
+};
+
+use anyhow::anyhow;
+use shared::models::websites::{
+    DatabaseWebsiteBackend, DatabaseWebsiteBackendProtocol, DatabaseWebsiteConfig,
+};
+use url::Host;
+
+use crate::{
+    dns,
+    proxy::{
+        backends::{BackendConnectionPool, BackendConnectionPoolConfig, BackendProtocol},
+        health::BackendHealth,
+        tls::build_client_config,
+    },
+};
+
+// ---------- 单个后端（每个后端独立的连接池）----------
+#[derive(Debug)]
+pub struct WebSiteBackend {
+    inner: DatabaseWebsiteBackend,
+    pool: Arc<BackendConnectionPool>,
+    health: BackendHealth,
+}
+
+impl WebSiteBackend {
+    pub async fn new(
+        inner: DatabaseWebsiteBackend,
+        config: &DatabaseWebsiteConfig,
+    ) -> anyhow::Result<Self> {
+        let hostname = inner.url.host_str().ok_or(anyhow!("No found any host"))?;
+        let port = inner.url.port_or_known_default().unwrap_or(80);
+        let resolved = dns::resolve(hostname).await?;
+        let addrs = resolved.socket_addrs(port);
+        let url = inner.url.clone();
+        let mut pool_config = BackendConnectionPoolConfig::new_from_targets(addrs)
+            .url(url)
+            .outlier_detection(config.outlier_detection.clone())
+            .connection_pool(&config.connection_pool)
+            .connect_timeout(Duration::from_secs(config.timeout.connect.max(1)))
+            .protocol(match inner.protocol {
+                DatabaseWebsiteBackendProtocol::Http1 => BackendProtocol::Http1,
+                DatabaseWebsiteBackendProtocol::Http2 | DatabaseWebsiteBackendProtocol::H2c => {
+                    BackendProtocol::Http2
+                }
+            });
+        if inner.url.scheme() == "https" {
+            let alpn: &[&[u8]] = match inner.protocol {
+                DatabaseWebsiteBackendProtocol::Http1 => &[b"http/1.1"],
+                _ => &[b"h2", b"http/1.1"],
+            };
+            let sni = inner.tls.sni.clone().or_else(|| match inner.url.host() {
+                Some(Host::Domain(domain)) => Some(domain.to_string()),
+                Some(Host::Ipv4(ip)) => Some(ip.to_string()),
+                Some(Host::Ipv6(ip)) => Some(ip.to_string()),
+                None => None,
+            });
+            pool_config = pool_config.tls(build_client_config(&inner.tls, alpn)?, sni);
+        }
+        let pool = BackendConnectionPool::new(pool_config);
+        if let Some(valid_until) = resolved.valid_until {
+            dns::spawn_refresh(
+                hostname.to_string(),
+                port,
+                Arc::downgrade(&pool),
+                valid_until,
+            );
+        }
+        Ok(Self {
+            inner,
+            pool,
+            health: BackendHealth::default(),
+        })
+    }
+
+    pub fn inner(&self) -> &DatabaseWebsiteBackend {
+        &self.inner
+    }
+
+    pub fn pool(&self) -> &Arc<BackendConnectionPool> {
+        &self.pool
+    }
+
+    pub fn health(&self) -> &BackendHealth {
+        &self.health
+    }
+
+    pub fn is_main(&self) -> bool {
+        self.inner.main
+    }
+
+    pub fn is_healthy(&self) -> bool {
+        self.health.is_healthy()
+    }
+
+    /// 主动健康检查通过，且仍有未被被动剔除的地址
+    pub fn is_available(&self) -> bool {
+        self.is_healthy() && self.pool.has_available_target()
+    }
+
+    /// 权重，0 视为 1
+    pub fn weight(&self) -> i64 {
+        self.inner.balance.max(1) as i64
+    }
+}
+
+// ---------- 后端组（平滑加权轮询）----------
+#[derive(Debug)]
+pub struct BackendGroup {
+    backends: Vec<Arc<WebSiteBackend>>,
+    current_weights: Mutex<Vec<i64>>,
+}
+
+impl BackendGroup {
+    pub async fn new(
+        backends: &[DatabaseWebsiteBackend],
+        config: &DatabaseWebsiteConfig,
+    ) -> anyhow::Result<Self> {
+        if backends.is_empty() {
+            return Err(anyhow!("No found any backends"));
+        }
+        let mut result = Vec::with_capacity(backends.len());
+        for backend in backends {
+            result.push(Arc::new(
+                WebSiteBackend::new(backend.clone(), config).await?,
+            ));
+        }
+        Ok(Self {
+            current_weights: Mutex::new(vec![0; result.len()]),
+            backends: result,
+        })
+    }
+
+    pub fn backends(&self) -> &[Arc<WebSiteBackend>] {
+        &self.backends
+    }
+
+    /// 按权重选出下一个后端：优先健康的主后端，其次健康的备用后端，
+    /// 全部不健康时仍尝试所有后端
+    pub fn select(&self) -> Option<Arc<WebSiteBackend>> {
+        self.select_by(|b| b.is_main() && b.is_available())
+            .or_else(|| self.select_by(|b| !b.is_main() && b.is_available()))
+            .or_else(|| self.select_by(|_| true))
+    }
+
+    /// 在满足条件的后端中按平滑加权轮询选择
+    pub fn select_by(
+        &self,
+        filter: impl Fn(&WebSiteBackend) -> bool,
+    ) -> Option<Arc<WebSiteBackend>> {
+        let mut current_weights = self.current_weights.lock().unwrap();
+        let mut total = 0;
+        let mut best: Option<usize> = None;
+        for (idx, backend) in self.backends.iter().enumerate() {
+            if !filter(backend) {
+                continue;
+            }
+            let weight = backend.weight();
+            current_weights[idx] += weight;
+            total += weight;
+            if best.is_none_or(|b| current_weights[idx] > current_weights[b]) {
+                best = Some(idx);
+            }
+        }
+        let best = best?;
+        current_weights[best] -= total;
+        Some(self.backends[best].clone())
+    }
+}
@@ -0,0 +1,196 @@
+use hyper::{
+    HeaderMap,
+    header::{HeaderName, HeaderValue, SERVER},
+};
+use rustls::ProtocolVersion;
+use shared::models::websites::{DatabaseWebsiteHeaderRule, DatabaseWebsiteHeaders};
+
+use crate::state::ClientState;
+
+// ---------- 变量 ----------
+#[derive(Debug)]
+enum Variable {
+    ClientIp,
+    RequestId,
+    Host,
+    Scheme,
+    TlsVersion,
+    Sni,
+}
+
+impl Variable {
+    fn parse(name: &str) -> anyhow::Result<Self> {
+        Ok(match name {
+            "client_ip" => Self::ClientIp,
+            "request_id" => Self::RequestId,
+            "host" => Self::Host,
+            "scheme" => Self::Scheme,
+            "tls_version" => Self::TlsVersion,
+            "sni" => Self::Sni,
+            _ => return Err(anyhow::anyhow!("Unknown header variable: {name}")),
+        })
+    }
+
+    /// 不存在时（如非 TLS 连接）为空字符串
+    fn render(&self, state: &ClientState, out: &mut String) {
+        match self {
+            Self::ClientIp => out.push_str(&state.remote_addr().to_string()),
+            Self::RequestId => out.push_str(&state.id().to_string()),
+            Self::Host => out.push_str(state.host()),
+            Self::Scheme => out.push_str(state.scheme()),
+            Self::TlsVersion => out.push_str(match state.base.tls_version {
+                Some(ProtocolVersion::TLSv1_3) => "TLSv1.3",
+                Some(ProtocolVersion::TLSv1_2) => "TLSv1.2",
+                Some(ProtocolVersion::TLSv1_1) => "TLSv1.1",
+                Some(ProtocolVersion::TLSv1_0) => "TLSv1.0",
+                _ => "",
+            }),
+            Self::Sni => {
+                if let Some(hostname) = state.tls().and_then(|v| v.hostname.as_deref()) {
+                    out.push_str(hostname)
+                }
+            }
+        }
+    }
+}
+
+// ---------- 带变量的值，如 `${client_ip}, proxy` ----------
+#[derive(Debug)]
+enum Segment {
+    Text(String),
+    Variable(Variable),
+}
+
+#[derive(Debug)]
+struct Template(Vec<Segment>);
+
+impl Template {
+    fn parse(value: &str) -> anyhow::Result<Self> {
+        let mut segments = vec![];
+        let mut rest = value;
+        while let Some(start) = rest.find("${") {
+            let end = rest[start..].find('}').ok_or(anyhow::anyhow!(
+                "Unclosed variable in header value: {value}"
+            ))?;
+            if start > 0 {
+                segments.push(Segment::Text(rest[..start].to_string()));
+            }
+            segments.push(Segment::Variable(Variable::parse(
+                &rest[start + 2..start + end],
+            )?));
+            rest = &rest[start + end + 1..];
+        }
+        if !rest.is_empty() {
+            segments.push(Segment::Text(rest.to_string()));
+        }
+        Ok(Self(segments))
+    }
+
+    fn render(&self, state: &ClientState) -> anyhow::Result<HeaderValue> {
+        let mut value = String::new();
+        for segment in &self.0 {
+            match segment {
+                Segment::Text(text) => value.push_str(text),
+                Segment::Variable(variable) => variable.render(state, &mut value),
+            }
+        }
+        Ok(HeaderValue::try_from(value)?)
+    }
+}
+
+// ---------- 规则 ----------
+#[derive(Debug)]
+enum HeaderRule {
+    Set(HeaderName, Template),
+    Append(HeaderName, Template),
+    Remove(HeaderName),
+}
+
+impl HeaderRule {
+    fn new(rule: &DatabaseWebsiteHeaderRule) -> anyhow::Result<Self> {
+        Ok(match rule {
+            DatabaseWebsiteHeaderRule::Set { name, value } => {
+                Self::Set(HeaderName::try_from(name)?, Template::parse(value)?)
+            }
+            DatabaseWebsiteHeaderRule::Append { name, value } => {
+                Self::Append(HeaderName::try_from(name)?, Template::parse(value)?)
+            }
+            DatabaseWebsiteHeaderRule::Remove { name } => Self::Remove(HeaderName::try_from(name)?),
+        })
+    }
+
+    fn apply(&self, headers: &mut HeaderMap, state: &ClientState) -> anyhow::Result<()> {
+        match self {
+            Self::Set(name, value) => {
+                headers.insert(name, value.render(state)?);
+            }
+            Self::Append(name, value) => {
+                headers.append(name, value.render(state)?);
+            }
+            Self::Remove(name) => {
+                headers.remove(name);
+            }
+        }
+        Ok(())
+    }
+}
+
+#[derive(Debug)]
+pub struct HeaderRules {
+    request: Vec<HeaderRule>,
+    response: Vec<HeaderRule>,
+    server: Option<HeaderValue>,
+}
+
+impl HeaderRules {
+    pub fn new(config: &DatabaseWebsiteHeaders) -> anyhow::Result<Self> {
+        Ok(Self {
+            request: config
+                .request
+                .iter()
+                .map(HeaderRule::new)
+                .collect::<anyhow::Result<_>>()?,
+            response: config
+                .response
+                .iter()
+                .map(HeaderRule::new)
+                .collect::<anyhow::Result<_>>()?,
+            server: config
+                .server
+                .as_deref()
+                .map(HeaderValue::from_str)
+                .transpose()?,
+        })
+    }
+
+    pub fn apply_request(
+        &self,
+        headers: &mut HeaderMap,
+        state: &ClientState,
+    ) -> anyhow::Result<()> {
+        for rule in &self.request {
+            rule.apply(headers, state)?;
+        }
+        Ok(())
+    }
+
+    /// 先替换（或删除）后端返回的 Server，再执行规则
+    pub fn apply_response(
+        &self,
+        headers: &mut HeaderMap,
+        state: &ClientState,
+    ) -> anyhow::Result<()> {
+        match &self.server {
+            Some(server) => {
+                headers.insert(SERVER, server.clone());
+            }
+            None => {
+                headers.remove(SERVER);
+            }
+        }
+        for rule in &self.response {
+            rule.apply(headers, state)?;
+        }
+        Ok(())
+    }
+}
@@ -0,0 +1,197 @@
+use std::{
+    sync::{
+        Arc, RwLock,
+        atomic::{AtomicBool, AtomicUsize, Ordering},
+    },
+    time::{Duration, Instant},
+};
+
+use bytes::Bytes;
+use chrono::{DateTime, Utc};
+use http_body_util::{BodyExt, Empty};
+use hyper::Request;
+use shared::{
+    database::{get_database, websites::DatabaseWebsiteModifyRepository},
+    models::websites::{DatabaseWebsiteBackendHealth, DatabaseWebsiteHealthCheck},
+    objectid::ObjectId,
+};
+use tokio::{task::JoinHandle, time::timeout};
+use tracing::{Level, event};
+
+use crate::proxy::group::WebSiteBackend;
+
+// 状态没有变化时，最多每隔这么久刷新一次检查记录
+const RECORD_INTERVAL: Duration = Duration::from_secs(60);
+
+// ---------- 后端健康状态（未配置检查时始终健康）----------
+#[derive(Debug)]
+pub struct BackendHealth {
+    healthy: AtomicBool,
+    successes: AtomicUsize,
+    failures: AtomicUsize,
+    changed_at: RwLock<DateTime<Utc>>,
+    recorded_at: RwLock<Option<Instant>>,
+}
+
+impl Default for BackendHealth {
+    fn default() -> Self {
+        Self {
+            healthy: AtomicBool::new(true),
+            successes: AtomicUsize::new(0),
+            failures: AtomicUsize::new(0),
+            changed_at: RwLock::new(Utc::now()),
+            recorded_at: RwLock::new(None),
+        }
+    }
+}
+
+impl BackendHealth {
+    pub fn is_healthy(&self) -> bool {
+        self.healthy.load(Ordering::Relaxed)
+    }
+
+    pub fn changed_at(&self) -> DateTime<Utc> {
+        *self.changed_at.read().unwrap()
+    }
+
+    /// 记录一次检查结果，连续 rise 次成功转为健康，连续 fall 次失败转为不健康。
+    /// 状态发生变化时返回 true
+    pub fn report(&self, success: bool, rise: usize, fall: usize) -> bool {
+        let (counter, other, threshold) = if success {
+            (&self.successes, &self.failures, rise)
+        } else {
+            (&self.failures, &self.successes, fall)
+        };
+        other.store(0, Ordering::Relaxed);
+        let count = counter.fetch_add(1, Ordering::Relaxed) + 1;
+        if count >= threshold.max(1) && self.is_healthy() != success {
+            self.healthy.store(success, Ordering::Relaxed);
+            *self.changed_at.write().unwrap() = Utc::now();
+            return true;
+        }
+        false
+    }
+
+    /// 状态变化、第一次检查或距上次写入超过 RECORD_INTERVAL 时需要写入记录
+    fn should_record(&self, changed: bool) -> bool {
+        let mut recorded_at = self.recorded_at.write().unwrap();
+        if !changed && recorded_at.is_some_and(|v| v.elapsed() < RECORD_INTERVAL) {
+            return false;
+        }
+        *recorded_at = Some(Instant::now());
+        true
+    }
+}
+
+pub fn spawn_health_check(
+    website_id: ObjectId,
+    route_id: String,
+    config: DatabaseWebsiteHealthCheck,
+    backends: Vec<Arc<WebSiteBackend>>,
+) -> JoinHandle<()> {
+    let config = Arc::new(config);
+    let route_id: Arc<str> = route_id.into();
+    tokio::spawn(async move {
+        loop {
+            let mut tasks = tokio::task::JoinSet::new();
+            for backend in &backends {
+                let backend = backend.clone();
+                let config = config.clone();
+                let route_id = route_id.clone();
+                tasks.spawn(async move {
+                    run_health_check(website_id, &route_id, &config, &backend).await;
+                });
+            }
+            tasks.join_all().await;
+            tokio::time::sleep(Duration::from_secs(config.interval.max(1))).await;
+        }
+    })
+}
+
+async fn run_health_check(
+    website_id: ObjectId,
+    route_id: &str,
+    config: &DatabaseWebsiteHealthCheck,
+    backend: &WebSiteBackend,
+) {
+    let result = match timeout(
+        Duration::from_secs(config.timeout.max(1)),
+        check_backend(config, backend),
+    )
+    .await
+    {
+        Ok(Ok(status)) if config.is_expected_status(status) => Ok(status),
+        Ok(Ok(status)) => Err((Some(status), format!("Unexpected status {status}"))),
+        Ok(Err(e)) => Err((None, e.to_string())),
+        Err(_) => Err((None, "Health check timeout".to_string())),
+    };
+    let health = backend.health();
+    let changed = health.report(result.is_ok(), config.rise, config.fall);
+    if changed {
+        event!(
+            Level::WARN,
+            "Backend {} of website {website_id} is now {}",
+            backend.inner().url,
+            if health.is_healthy() {
+                "healthy"
+            } else {
+                "unhealthy"
+            }
+        );
+    }
+    if !health.should_record(changed) {
+        return;
+    }
+    let (status, error) = match result {
+        Ok(status) => (Some(status), None),
+        Err((status, error)) => (status, Some(error)),
+    };
+    let record = DatabaseWebsiteBackendHealth {
+        website_id,
+        route: route_id.to_string(),
+        url: backend.inner().url.clone(),
+        main: backend.inner().main,
+        healthy: health.is_healthy(),
+        status,
+        error,
+        checked_at: Utc::now(),
+        changed_at: health.changed_at(),
+    };
+    if let Err(e) = get_database().update_website_backend_health(&record).await {
+        event!(Level::ERROR, "Failed to update backend health: {e}");
+    }
+}
+
+async fn check_backend(
+    config: &DatabaseWebsiteHealthCheck,
+    backend: &WebSiteBackend,
+) -> anyhow::Result<u16> {
+    let mut sender = backend.pool().connect().await?;
+    let url = &backend.inner().url;
+    let host = match (&backend.inner().host, url.port()) {
+        (Some(host), _) => host.to_string(),
+        (None, Some(port)) => format!("{}:{port}", url.host_str().unwrap_or_default()),
+        (None, None) => url.host_str().unwrap_or_default().to_string(),
+    };
+    let req = Request::get(config.path.as_str())
+        .header("Host", host)
+        .header("User-Agent", "WebGateway-HealthCheck")
+        .body(Empty::<Bytes>::new().map_err(|e| e.into()).boxed_unsync())?;
+    let resp = sender.send_request(req).await?;
+    Ok(resp.status().as_u16())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn records_only_on_change_or_interval() {
+        let health = BackendHealth::default();
+        assert!(health.should_record(false));
+        assert!(!health.should_record(false));
+        assert!(health.should_record(true));
+        *health.recorded_at.write().unwrap() = Instant::now().checked_sub(RECORD_INTERVAL);
+        assert!(health.should_record(false));
+    }
+}
@@ -0,0 +1,148 @@
+use std::{
+    net::SocketAddr,
+    sync::{
+        Arc, Mutex,
+        atomic::{AtomicBool, AtomicU32, AtomicUsize, Ordering},
+    },
+    time::{Duration, Instant},
+};
+
+use dashmap::DashMap;
+use shared::models::websites::DatabaseWebsiteOutlierDetection;
+use tracing::{Level, event};
+
+// ---------- 单个地址的异常状态 ----------
+#[derive(Debug, Default)]
+struct AddressState {
+    failures: AtomicUsize,
+    ejections: AtomicU32,
+    ejected_until: Mutex<Option<Instant>>,
+    // 冷却结束后只放行一个探测请求（半开）
+    probing: AtomicBool,
+}
+
+// ---------- 被动异常检测（按后端地址统计）----------
+#[derive(Debug)]
+pub struct OutlierDetector {
+    config: DatabaseWebsiteOutlierDetection,
+    states: DashMap<SocketAddr, Arc<AddressState>>,
+}
+
+impl OutlierDetector {
+    pub fn new(config: DatabaseWebsiteOutlierDetection) -> Self {
+        Self {
+            config,
+            states: DashMap::new(),
+        }
+    }
+
+    fn state(&self, addr: SocketAddr) -> Arc<AddressState> {
+        self.states.entry(addr).or_default().clone()
+    }
+
+    /// 地址当前是否被剔除（不占用半开探测名额）
+    pub fn is_ejected(&self, addr: SocketAddr) -> bool {
+        if !self.config.enabled {
+            return false;
+        }
+        let Some(state) = self.states.get(&addr).map(|v| v.clone()) else {
+            return false;
+        };
+        match *state.ejected_until.lock().unwrap() {
+            Some(until) if until > Instant::now() => true,
+            Some(_) => state.probing.load(Ordering::Acquire),
+            None => false,
+        }
+    }
+
+    /// 尝试使用该地址，冷却期内返回 false，冷却结束后只允许一个探测请求通过
+    pub fn try_acquire(&self, addr: SocketAddr) -> bool {
+        if !self.config.enabled {
+            return true;
+        }
+        let state = self.state(addr);
+        match *state.ejected_until.lock().unwrap() {
+            Some(until) if until > Instant::now() => false,
+            Some(_) => state
+                .probing
+                .compare_exchange(false, true, Ordering::AcqRel, Ordering::Acquire)
+                .is_ok(),
+            None => true,
+        }
+    }
+
+    pub fn report_success(&self, addr: SocketAddr) {
+        if !self.config.enabled {
+            return;
+        }
+        let state = self.state(addr);
+        state.failures.store(0, Ordering::Relaxed);
+        let mut ejected_until = state.ejected_until.lock().unwrap();
+        if ejected_until.take().is_some() {
+            event!(Level::INFO, "Backend {addr} recovered, restore traffic");
+        }
+        state.ejections.store(0, Ordering::Relaxed);
+        state.probing.store(false, Ordering::Release);
+    }
+
+    pub fn report_failure(&self, addr: SocketAddr) {
+        if !self.config.enabled {
+            return;
+        }
+        let state = self.state(addr);
+        let probing = state.probing.swap(false, Ordering::AcqRel);
+        let failures = state.failures.fetch_add(1, Ordering::Relaxed) + 1;
+        if !probing && failures < self.config.consecutive_failures.max(1) {
+            return;
+        }
+        // 半开探测失败或连续失败达到阈值，按剔除次数指数退避
+        let ejections = state.ejections.fetch_add(1, Ordering::Relaxed);
+        let duration = Duration::from_secs(self.config.ejection_time)
+            .saturating_mul(2u32.saturating_pow(ejections))
+            .min(Duration::from_secs(
+                self.config.max_ejection_time.max(self.config.ejection_time),
+            ));
+        state.failures.store(0, Ordering::Relaxed);
+        *state.ejected_until.lock().unwrap() = Some(Instant::now() + duration);
+        event!(
+            Level::WARN,
+            "Backend {addr} ejected for {duration:?} after {failures} consecutive failures"
+        );
+    }
+}
+
+// ---------- 一次请求的结果（未显式上报即视为失败，如超时被取消）----------
+#[derive(Debug)]
+pub struct OutlierOutcome {
+    detector: Arc<OutlierDetector>,
+    addr: SocketAddr,
+    reported: bool,
+}
+
+impl OutlierOutcome {
+    pub fn new(detector: Arc<OutlierDetector>, addr: SocketAddr) -> Self {
+        Self {
+            detector,
+            addr,
+            reported: false,
+        }
+    }
+
+    pub fn success(mut self) {
+        self.reported = true;
+        self.detector.report_success(self.addr);
+    }
+
+    pub fn failure(mut self) {
+        self.reported = true;
+        self.detector.report_failure(self.addr);
+    }
+}
+
+impl Drop for OutlierOutcome {
+    fn drop(&mut self) {
+        if !self.reported {
+            self.detector.report_failure(self.addr);
+        }
+    }
+}
@@ -0,0 +1,215 @@
+use std::{
+    collections::HashMap,
+    sync::Arc,
+    time::{Duration, Instant},
+};
+
+use dashmap::DashMap;
+use hyper::{HeaderMap, header::HeaderName};
+use shared::{
+    database::{get_database, ratelimit::DatabaseRateLimitRepository},
+    models::websites::{DatabaseWebsiteRateLimit, DatabaseWebsiteRateLimitKey},
+};
+use tokio::task::JoinHandle;
+use tracing::{Level, event};
+
+use crate::state::ClientState;
+
+// 清理空闲的桶并与其他实例同步计数的间隔
+const SYNC_INTERVAL: Duration = Duration::from_secs(1);
+// 数据库中超过此时间没有更新的计数会被删除，单位为秒
+const SHARED_IDLE: u64 = 3600;
+
+#[derive(Debug)]
+enum RateLimitKey {
+    Ip,
+    Header(HeaderName),
+    Path,
+}
+
+#[derive(Debug)]
+struct Bucket {
+    tokens: f64,
+    updated_at: Instant,
+    /// 上次同步后本实例消耗的令牌
+    pending: f64,
+    /// 上次同步时数据库中的总数
+    seen: Option<f64>,
+}
+
+impl Bucket {
+    fn refill(&mut self, capacity: f64, rate: f64) {
+        let now = Instant::now();
+        let elapsed = now.duration_since(self.updated_at).as_secs_f64();
+        self.tokens = (self.tokens + elapsed * rate).min(capacity);
+        self.updated_at = now;
+    }
+}
+
+// ---------- 单条限流规则 ----------
+#[derive(Debug)]
+struct RateLimitRule {
+    key: RateLimitKey,
+    capacity: f64,
+    /// 每秒补充的令牌
+    rate: f64,
+    shared: bool,
+    /// 共享计数在数据库中的前缀
+    prefix: String,
+    buckets: DashMap<String, Bucket>,
+}
+
+impl RateLimitRule {
+    fn new(prefix: String, config: &DatabaseWebsiteRateLimit) -> anyhow::Result<Self> {
+        if config.requests == 0 || config.period == 0 {
+            return Err(anyhow::anyhow!(
+                "Rate limit requests and period must be greater than 0"
+            ));
+        }
+        Ok(Self {
+            key: match &config.key {
+                DatabaseWebsiteRateLimitKey::Ip => RateLimitKey::Ip,
+                DatabaseWebsiteRateLimitKey::Header(v) => {
+                    RateLimitKey::Header(HeaderName::from_bytes(v.as_bytes())?)
+                }
+                DatabaseWebsiteRateLimitKey::Path => RateLimitKey::Path,
+            },
+            capacity: match config.burst {
+                0 => config.requests,
+                v => v,
+            } as f64,
+            rate: config.requests as f64 / config.period as f64,
+            shared: config.shared,
+            prefix,
+            buckets: DashMap::new(),
+        })
+    }
+
+    fn key(&self, state: &ClientState, path: &str, headers: &HeaderMap) -> Option<String> {
+        match &self.key {
+            RateLimitKey::Ip => Some(state.remote_addr().to_string()),
+            RateLimitKey::Header(name) => headers
+                .get(name)
+                .map(|v| String::from_utf8_lossy(v.as_bytes()).into_owned()),
+            RateLimitKey::Path => Some(path.to_string()),
+        }
+    }
+
+    /// 令牌不足时返回需要等待的时间
+    fn take(&self, key: String) -> Result<(), Duration> {
+        let mut bucket = self.buckets.entry(key).or_insert_with(|| Bucket {
+            tokens: self.capacity,
+            updated_at: Instant::now(),
+            pending: 0.0,
+            seen: None,
+        });
+        bucket.refill(self.capacity, self.rate);
+        if bucket.tokens < 1.0 {
+            return Err(Duration::from_secs_f64((1.0 - bucket.tokens) / self.rate));
+        }
+        bucket.tokens -= 1.0;
+        bucket.pending += 1.0;
+        Ok(())
+    }
+
+    /// 已经补满且没有待同步数据的桶与新建的桶没有区别
+    fn clean(&self) {
+        self.buckets.retain(|_, bucket| {
+            bucket.refill(self.capacity, self.rate);
+            bucket.tokens < self.capacity || bucket.pending > 0.0
+        });
+    }
+
+    /// 上报本实例的消耗，并扣除其他实例在此期间的消耗
+    async fn sync(&self) -> anyhow::Result<()> {
+        let consumed = self
+            .buckets
+            .iter_mut()
+            .map(|mut v| {
+                let pending = std::mem::take(&mut v.pending);
+                (format!("{}:{}", self.prefix, v.key()), pending)
+            })
+            .collect::<Vec<_>>();
+        if consumed.is_empty() {
+            return Ok(());
+        }
+        let sent = consumed.iter().cloned().collect::<HashMap<_, _>>();
+        for (key, total) in get_database().add_rate_limit_consumed(consumed).await? {
+            let Some(local) = sent.get(&key) else {
+                continue;
+            };
+            let Some(mut bucket) = key
+                .strip_prefix(&format!("{}:", self.prefix))
+                .and_then(|v| self.buckets.get_mut(v))
+            else {
+                continue;
+            };
+            // 第一次同步时只记录总数，之前的消耗不再扣除
+            let others = bucket.seen.map_or(0.0, |seen| total - seen - local);
+            bucket.tokens = (bucket.tokens - others.max(0.0)).max(0.0);
+            bucket.seen = Some(total);
+        }
+        Ok(())
+    }
+}
+
+// ---------- 路由的限流规则 ----------
+#[derive(Debug)]
+pub struct RateLimiter {
+    rules: Arc<Vec<RateLimitRule>>,
+    task: Option<JoinHandle<()>>,
+}
+
+impl RateLimiter {
+    /// scope 区分不同网站和路由的共享计数
+    pub fn new(scope: &str, config: &[DatabaseWebsiteRateLimit]) -> anyhow::Result<Self> {
+        let rules = Arc::new(
+            config
+                .iter()
+                .enumerate()
+                .map(|(index, v)| RateLimitRule::new(format!("{scope}:{index}"), v))
+                .collect::<anyhow::Result<Vec<_>>>()?,
+        );
+        let task = (!rules.is_empty()).then(|| tokio::spawn(background(rules.clone())));
+        Ok(Self { rules, task })
+    }
+
+    /// 超出限制时返回客户端需要等待的时间
+    pub fn check(&self, state: &ClientState, path: &str, headers: &HeaderMap) -> Option<Duration> {
+        self.rules.iter().find_map(|rule| {
+            rule.key(state, path, headers)
+                .and_then(|key| rule.take(key).err())
+        })
+    }
+}
+
+impl Drop for RateLimiter {
+    fn drop(&mut self) {
+        if let Some(task) = self.task.take() {
+            task.abort();
+        }
+    }
+}
+
+async fn background(rules: Arc<Vec<RateLimitRule>>) {
+    let mut interval = tokio::time::interval(SYNC_INTERVAL);
+    let mut ticks: u64 = 0;
+    loop {
+        interval.tick().await;
+        ticks += 1;
+        for rule in rules.iter() {
+            if rule.shared
+                && let Err(e) = rule.sync().await
+            {
+                event!(Level::WARN, "Failed to sync rate limit counters: {e}");
+            }
+            rule.clean();
+        }
+        if ticks.is_multiple_of(SHARED_IDLE)
+            && rules.iter().any(|v| v.shared)
+            && let Err(e) = get_database().clean_rate_limit_counters(SHARED_IDLE).await
+        {
+            event!(Level::WARN, "Failed to clean rate limit counters: {e}");
+        }
+    }
+}
@@ -0,0 +1,181 @@
+use hyper::{StatusCode, Uri, header::LOCATION};
+use regex::Regex;
+use shared::models::websites::{
+    DatabaseWebsiteForceHttps, DatabaseWebsiteRedirect, DatabaseWebsiteRedirectRule,
+};
+
+use crate::{state::ClientState, transport::CResponse};
+
+fn redirect_status(status: u16) -> anyhow::Result<StatusCode> {
+    let status = StatusCode::from_u16(status)?;
+    match status.is_redirection() && status != StatusCode::NOT_MODIFIED {
+        true => Ok(status),
+        false => Err(anyhow::anyhow!("Invalid redirect status: {status}")),
+    }
+}
+
+/// 去掉 Host 中的端口，支持 `[::1]:80` 形式
+fn strip_port(host: &str) -> &str {
+    if host.starts_with('[') {
+        return host.find(']').map_or(host, |i| &host[..=i]);
+    }
+    match host.rsplit_once(':') {
+        Some((name, port)) if port.bytes().all(|v| v.is_ascii_digit()) => name,
+        _ => host,
+    }
+}
+
+// ---------- 跳转规则 ----------
+#[derive(Debug)]
+struct RedirectRule {
+    host: Option<String>,
+    path: Option<Regex>,
+    to_scheme: Option<String>,
+    to_host: Option<String>,
+    to_path: Option<String>,
+    status: StatusCode,
+    preserve_query: bool,
+}
+
+impl RedirectRule {
+    /// host 和 path 至少需要一个，否则会匹配所有请求（跳转到自身时形成循环）
+    fn new(rule: &DatabaseWebsiteRedirectRule) -> anyhow::Result<Self> {
+        if rule.host.is_none() && rule.path.is_none() {
+            return Err(anyhow::anyhow!(
+                "Redirect rule requires a host or path condition"
+            ));
+        }
+        Ok(Self {
+            host: rule.host.as_ref().map(|v| v.to_ascii_lowercase()),
+            path: rule.path.as_deref().map(Regex::new).transpose()?,
+            to_scheme: rule.to_scheme.clone(),
+            to_host: rule.to_host.clone(),
+            to_path: rule.to_path.clone(),
+            status: redirect_status(rule.status)?,
+            preserve_query: rule.preserve_query,
+        })
+    }
+
+    /// 匹配时返回跳转地址
+    fn location(&self, state: &ClientState, uri: &Uri) -> Option<String> {
+        if let Some(host) = &self.host
+            && !strip_port(state.host()).eq_ignore_ascii_case(host)
+        {
+            return None;
+        }
+        let mut path = uri.path().to_string();
+        if let Some(regex) = &self.path {
+            let captures = regex.captures(uri.path())?;
+            if let Some(to_path) = &self.to_path {
+                path.clear();
+                captures.expand(to_path, &mut path);
+            }
+        } else if let Some(to_path) = &self.to_path {
+            path = to_path.clone();
+        }
+        let mut location = format!(
+            "{}://{}{}",
+            self.to_scheme.as_deref().unwrap_or(state.scheme()),
+            self.to_host.as_deref().unwrap_or(state.host()),
+            path
+        );
+        if self.preserve_query
+            && let Some(query) = uri.query()
+        {
+            location.push(if path.contains('?') { '&' } else { '?' });
+            location.push_str(query);
+        }
+        Some(location)
+    }
+}
+
+#[derive(Debug)]
+pub struct Redirects {
+    force_https: Option<(StatusCode, u16)>,
+    rules: Vec<RedirectRule>,
+}
+
+impl Redirects {
+    pub fn new(config: &DatabaseWebsiteRedirect) -> anyhow::Result<Self> {
+        Ok(Self {
+            force_https: config
+                .force_https
+                .as_ref()
+                .map(|DatabaseWebsiteForceHttps { status, port }| {
+                    Ok::<_, anyhow::Error>((redirect_status(*status)?, *port))
+                })
+                .transpose()?,
+            rules: config
+                .rules
+                .iter()
+                .map(RedirectRule::new)
+                .collect::<anyhow::Result<_>>()?,
+        })
+    }
+
+    /// 需要跳转时返回跳转响应
+    pub fn redirect(
+        &self,
+        state: &ClientState,
+        uri: &Uri,
+    ) -> anyhow::Result<Option<hyper::Response<CResponse>>> {
+        let location = match self.force_https {
+            Some((status, port)) if state.tls().is_none() => {
+                let host = strip_port(state.host());
+                let target = uri.path_and_query().map_or("/", |v| v.as_str());
+                Some((
+                    status,
+                    match port {
+                        443 => format!("https://{host}{target}"),
+                        port => format!("https://{host}:{port}{target}"),
+                    },
+                ))
+            }
+            _ => self
+                .rules
+                .iter()
+                .find_map(|rule| rule.location(state, uri).map(|v| (rule.status, v))),
+        };
+        let Some((status, location)) = location else {
+            return Ok(None);
+        };
+        Ok(Some(
+            hyper::Response::builder()
+                .status(status)
+                .header(LOCATION, location)
+                .body(CResponse::new_from_string(""))?,
+        ))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn strips_port() {
+        assert_eq!(strip_port("example.com"), "example.com");
+        assert_eq!(strip_port("example.com:8080"), "example.com");
+        assert_eq!(strip_port("example.com:"), "example.com");
+        assert_eq!(strip_port("127.0.0.1:80"), "127.0.0.1");
+        assert_eq!(strip_port("[::1]:443"), "[::1]");
+        assert_eq!(strip_port("[::1]"), "[::1]");
+        assert_eq!(strip_port("example.com:http"), "example.com:http");
+    }
+
+    #[test]
+    fn rejects_rule_without_condition() {
+        let mut rule = DatabaseWebsiteRedirectRule {
+            host: None,
+            path: None,
+            to_scheme: None,
+            to_host: Some("example.com".to_string()),
+            to_path: None,
+            status: 301,
+            preserve_query: true,
+        };
+        assert!(RedirectRule::new(&rule).is_err());
+        rule.host = Some("www.example.com".to_string());
+        assert!(RedirectRule::new(&rule).is_ok());
+    }
+}
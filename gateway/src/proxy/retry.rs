@@ -0,0 +1,104 @@
+use std::{
+    sync::Mutex,
+    time::{Duration, Instant},
+};
+
+use bytes::Bytes;
+use http_body_util::{BodyExt, Full};
+use hyper::Method;
+use shared::models::websites::DatabaseWebsiteRetry;
+
+use crate::transport::CRequest;
+
+// 预算按固定窗口统计
+const BUDGET_WINDOW: Duration = Duration::from_secs(10);
+
+#[derive(Debug)]
+struct BudgetWindow {
+    started_at: Instant,
+    requests: usize,
+    retries: usize,
+}
+
+// ---------- 重试预算（防止后端故障时重试放大流量）----------
+#[derive(Debug)]
+pub struct RetryBudget {
+    ratio: f64,
+    min_per_second: usize,
+    window: Mutex<BudgetWindow>,
+}
+
+impl RetryBudget {
+    pub fn new(config: &DatabaseWebsiteRetry) -> Self {
+        Self {
+            ratio: config.budget_ratio.max(0.0),
+            min_per_second: config.budget_min_per_second,
+            window: Mutex::new(BudgetWindow {
+                started_at: Instant::now(),
+                requests: 0,
+                retries: 0,
+            }),
+        }
+    }
+
+    fn window(&self) -> std::sync::MutexGuard<'_, BudgetWindow> {
+        let mut window = self.window.lock().unwrap();
+        if window.started_at.elapsed() >= BUDGET_WINDOW {
+            *window = BudgetWindow {
+                started_at: Instant::now(),
+                requests: 0,
+                retries: 0,
+            };
+        }
+        window
+    }
+
+    pub fn record_request(&self) {
+        self.window().requests += 1;
+    }
+
+    /// 预算充足时占用一次重试
+    pub fn try_retry(&self) -> bool {
+        let mut window = self.window();
+        let allowed = ((window.requests as f64 * self.ratio) as usize)
+            .max(self.min_per_second * BUDGET_WINDOW.as_secs() as usize);
+        if window.retries >= allowed {
+            return false;
+        }
+        window.retries += 1;
+        true
+    }
+}
+
+/// 重复执行不会产生额外副作用的方法
+pub fn is_idempotent(method: &Method) -> bool {
+    matches!(
+        *method,
+        Method::GET | Method::HEAD | Method::OPTIONS | Method::TRACE | Method::PUT | Method::DELETE
+    )
+}
+
+// ---------- 发往后端的请求 body ----------
+pub enum RetryBody {
+    /// 已缓存，可以多次发送
+    Replay(Bytes),
+    /// 流式转发，只能发送一次
+    Stream(Option<CRequest>),
+}
+
+impl RetryBody {
+    pub fn is_replayable(&self) -> bool {
+        matches!(self, Self::Replay(_))
+    }
+
+    pub fn take(&mut self) -> anyhow::Result<CRequest> {
+        match self {
+            Self::Replay(bytes) => Ok(Full::new(bytes.clone())
+                .map_err(|e| match e {})
+                .boxed_unsync()),
+            Self::Stream(body) => body
+                .take()
+                .ok_or(anyhow::anyhow!("Request body has been consumed")),
+        }
+    }
+}
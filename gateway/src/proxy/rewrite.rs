@@ -0,0 +1,217 @@
+use regex::Regex;
+use shared::models::websites::DatabaseWebsiteRewrite;
+use url::form_urlencoded;
+
+// ---------- 转发前的路径和参数改写 ----------
+#[derive(Debug)]
+pub struct Rewriter {
+    strip_prefix: Option<String>,
+    regex: Vec<(Regex, String)>,
+    add_prefix: Option<String>,
+    query_add: Vec<(String, String)>,
+    query_remove: Vec<String>,
+}
+
+impl Rewriter {
+    pub fn new(config: &DatabaseWebsiteRewrite) -> anyhow::Result<Self> {
+        Ok(Self {
+            strip_prefix: config
+                .strip_prefix
+                .as_ref()
+                .map(|v| v.trim_end_matches('/').to_string())
+                .filter(|v| !v.is_empty()),
+            regex: config
+                .regex
+                .iter()
+                .map(|v| Ok((Regex::new(&v.pattern)?, v.replacement.clone())))
+                .collect::<anyhow::Result<_>>()?,
+            add_prefix: config
+                .add_prefix
+                .as_ref()
+                .map(|v| v.trim_end_matches('/').to_string())
+                .filter(|v| !v.is_empty()),
+            query_add: config
+                .query_add
+                .iter()
+                .map(|v| (v.name.clone(), v.value.clone()))
+                .collect(),
+            query_remove: config.query_remove.clone(),
+        })
+    }
+
+    /// 改写路径和参数。regex 替换结果中 `?` 之后的部分放在原参数之前，
+    /// 再一起执行参数规则；`#` 之后的部分丢弃
+    pub fn rewrite(&self, path: &str, query: Option<&str>) -> (String, Option<String>) {
+        let mut path = self.rewrite_path(path);
+        path.truncate(path.find('#').unwrap_or(path.len()));
+        let extra = path.find('?').map(|index| {
+            let extra = path[index + 1..].to_string();
+            path.truncate(index);
+            extra
+        });
+        let query = match (extra, query) {
+            (Some(extra), Some(query)) if !extra.is_empty() && !query.is_empty() => {
+                Some(format!("{extra}&{query}"))
+            }
+            (Some(extra), Some(query)) if extra.is_empty() => Some(query.to_string()),
+            (Some(extra), _) => Some(extra),
+            (None, query) => query.map(|v| v.to_string()),
+        };
+        (path, self.rewrite_query(query.as_deref()))
+    }
+
+    /// 依次执行 strip_prefix、regex、add_prefix，结果总是以 `/` 开头
+    fn rewrite_path(&self, path: &str) -> String {
+        let mut path = path.to_string();
+        if let Some(prefix) = &self.strip_prefix
+            && let Some(rest) = path.strip_prefix(prefix.as_str())
+            && (rest.is_empty() || rest.starts_with('/'))
+        {
+            path = rest.to_string();
+        }
+        for (regex, replacement) in &self.regex {
+            path = regex.replace(&path, replacement.as_str()).into_owned();
+        }
+        if !path.starts_with('/') {
+            path.insert(0, '/');
+        }
+        if let Some(prefix) = &self.add_prefix {
+            path.insert_str(0, prefix);
+        }
+        path
+    }
+
+    /// 删除和添加参数，其余参数保持原样（包括编码）
+    fn rewrite_query(&self, query: Option<&str>) -> Option<String> {
+        if self.query_add.is_empty() && self.query_remove.is_empty() {
+            return query.map(|v| v.to_string());
+        }
+        let mut pairs = query
+            .unwrap_or_default()
+            .split('&')
+            .filter(|v| !v.is_empty())
+            .filter(|v| {
+                let name = query_name(v);
+                !self.query_remove.contains(&name)
+                    && !self.query_add.iter().any(|(a, _)| *a == name)
+            })
+            .map(|v| v.to_string())
+            .collect::<Vec<_>>();
+        for (name, value) in &self.query_add {
+            pairs.push(format!(
+                "{}={}",
+                form_urlencoded::byte_serialize(name.as_bytes()).collect::<String>(),
+                form_urlencoded::byte_serialize(value.as_bytes()).collect::<String>()
+            ));
+        }
+        match pairs.is_empty() {
+            true => None,
+            false => Some(pairs.join("&")),
+        }
+    }
+}
+
+/// 解码后的参数名
+fn query_name(pair: &str) -> String {
+    form_urlencoded::parse(pair.as_bytes())
+        .next()
+        .map(|(name, _)| name.into_owned())
+        .unwrap_or_default()
+}
+
+#[cfg(test)]
+mod tests {
+    use hyper::Method;
+    use shared::models::websites::{DatabaseWebsiteQueryParam, DatabaseWebsiteRewriteRegex};
+
+    use super::*;
+    use crate::proxy::target::build_target;
+
+    fn rewriter(config: DatabaseWebsiteRewrite) -> Rewriter {
+        Rewriter::new(&config).unwrap()
+    }
+
+    #[test]
+    fn strips_prefix_on_segment_boundary() {
+        let rewrite = rewriter(DatabaseWebsiteRewrite {
+            strip_prefix: Some("/api/".to_string()),
+            ..Default::default()
+        });
+        assert_eq!(rewrite.rewrite_path("/api/users"), "/users");
+        assert_eq!(rewrite.rewrite_path("/api"), "/");
+        assert_eq!(rewrite.rewrite_path("/apis"), "/apis");
+    }
+
+    #[test]
+    fn applies_regex_then_add_prefix() {
+        let rewrite = rewriter(DatabaseWebsiteRewrite {
+            regex: vec![DatabaseWebsiteRewriteRegex {
+                pattern: "^/users/(?<id>\\d+)$".to_string(),
+                replacement: "/user?id=${id}".to_string(),
+            }],
+            add_prefix: Some("/v2/".to_string()),
+            ..Default::default()
+        });
+        assert_eq!(rewrite.rewrite_path("/other"), "/v2/other");
+        let target = |uri: &str| {
+            build_target(&Method::GET, &uri.parse().unwrap(), None, Some(&rewrite))
+                .unwrap()
+                .to_string()
+        };
+        assert_eq!(target("/users/42"), "/v2/user?id=42");
+        assert_eq!(target("/users/42?a=1"), "/v2/user?id=42&a=1");
+        assert_eq!(target("/users/42?"), "/v2/user?id=42");
+    }
+
+    #[test]
+    fn applies_query_rules_to_regex_query() {
+        let rewrite = rewriter(DatabaseWebsiteRewrite {
+            regex: vec![DatabaseWebsiteRewriteRegex {
+                pattern: "^/old$".to_string(),
+                replacement: "/new?debug=1&v=2#top".to_string(),
+            }],
+            query_remove: vec!["debug".to_string()],
+            ..Default::default()
+        });
+        assert_eq!(
+            rewrite.rewrite("/old", Some("a=1")),
+            ("/new".to_string(), Some("v=2&a=1".to_string()))
+        );
+        assert_eq!(
+            rewrite.rewrite("/other", Some("debug=1")),
+            ("/other".to_string(), None)
+        );
+    }
+
+    #[test]
+    fn keeps_query_without_rules() {
+        let rewrite = rewriter(DatabaseWebsiteRewrite::default());
+        assert_eq!(
+            rewrite.rewrite_query(Some("a=%20&b")).as_deref(),
+            Some("a=%20&b")
+        );
+        assert_eq!(rewrite.rewrite_query(None), None);
+    }
+
+    #[test]
+    fn adds_and_removes_query() {
+        let rewrite = rewriter(DatabaseWebsiteRewrite {
+            query_add: vec![DatabaseWebsiteQueryParam {
+                name: "lang".to_string(),
+                value: "zh cn".to_string(),
+            }],
+            query_remove: vec!["token".to_string()],
+            ..Default::default()
+        });
+        assert_eq!(
+            rewrite
+                .rewrite_query(Some("a=%20&tok%65n=x&lang=en"))
+                .as_deref(),
+            Some("a=%20&lang=zh+cn")
+        );
+        assert_eq!(
+            rewrite.rewrite_query(Some("token=x")).as_deref(),
+            Some("lang=zh+cn")
+        );
+    }
+}
@@ -0,0 +1,279 @@
+use std::sync::Arc;
+
+use geoip::LookupResult;
+use hyper::{HeaderMap, Method, header::HeaderName};
+use regex::Regex;
+use shared::{
+    models::websites::{
+        DatabaseWebsiteBackend, DatabaseWebsiteConfig, DatabaseWebsiteGeoBackends,
+        DatabaseWebsiteRoute, DatabaseWebsiteRoutePath,
+    },
+    objectid::ObjectId,
+};
+use tokio::task::JoinHandle;
+
+use crate::proxy::{
+    basicauth::BasicAuth,
+    cache::{ResponseCache, remove_disk_cache},
+    compression::Compression,
+    files::StaticFiles,
+    forwardauth::ForwardAuth,
+    geo::GeoMatcher,
+    group::BackendGroup,
+    headers::HeaderRules,
+    health::spawn_health_check,
+    ratelimit::RateLimiter,
+    redirect::Redirects,
+    retry::RetryBudget,
+    rewrite::Rewriter,
+};
+
+// ---------- 路径匹配 ----------
+#[derive(Debug)]
+enum PathMatcher {
+    Exact(String),
+    Prefix(String),
+    Regex(Regex),
+}
+
+impl PathMatcher {
+    fn new(path: &DatabaseWebsiteRoutePath) -> anyhow::Result<Self> {
+        Ok(match path {
+            DatabaseWebsiteRoutePath::Exact(v) => Self::Exact(v.clone()),
+            DatabaseWebsiteRoutePath::Prefix(v) => Self::Prefix(v.clone()),
+            DatabaseWebsiteRoutePath::Regex(v) => Self::Regex(Regex::new(v)?),
+        })
+    }
+
+    fn is_match(&self, path: &str) -> bool {
+        match self {
+            Self::Exact(v) => path == v,
+            Self::Prefix(v) => {
+                path.starts_with(v.as_str())
+                    && (v.ends_with('/')
+                        || path.len() == v.len()
+                        || path.as_bytes()[v.len()] == b'/')
+            }
+            Self::Regex(v) => v.is_match(path),
+        }
+    }
+}
+
+// ---------- 路由条件 ----------
+#[derive(Debug)]
+pub struct RouteMatcher {
+    path: PathMatcher,
+    methods: Vec<Method>,
+    headers: Vec<(HeaderName, Option<String>)>,
+}
+
+impl RouteMatcher {
+    pub fn new(route: &DatabaseWebsiteRoute) -> anyhow::Result<Self> {
+        Ok(Self {
+            path: PathMatcher::new(&route.path)?,
+            methods: route
+                .methods
+                .iter()
+                .map(|v| Method::from_bytes(v.to_ascii_uppercase().as_bytes()))
+                .collect::<Result<_, _>>()?,
+            headers: route
+                .headers
+                .iter()
+                .map(|v| Ok((HeaderName::from_bytes(v.name.as_bytes())?, v.value.clone())))
+                .collect::<anyhow::Result<_>>()?,
+        })
+    }
+
+    pub fn is_match(&self, method: &Method, path: &str, headers: &HeaderMap) -> bool {
+        self.path.is_match(path)
+            && (self.methods.is_empty() || self.methods.contains(method))
+            && self.headers.iter().all(|(name, value)| match value {
+                Some(value) => headers
+                    .get_all(name)
+                    .iter()
+                    .any(|v| v.as_bytes() == value.as_bytes()),
+                None => headers.contains_key(name),
+            })
+    }
+}
+
+// ---------- 路由（各自的后端组和配置）----------
+#[derive(Debug)]
+pub struct RouteRunner {
+    matcher: Option<RouteMatcher>,
+    rewrite: Option<Rewriter>,
+    config: DatabaseWebsiteConfig,
+    headers: HeaderRules,
+    redirects: Redirects,
+    compression: Compression,
+    rate_limiter: RateLimiter,
+    basic_auth: Option<BasicAuth>,
+    forward_auth: Option<ForwardAuth>,
+    /// 只缓存后端组的响应
+    cache: Option<Arc<ResponseCache>>,
+    /// file:// 后端时由网关返回文件，不需要后端组
+    files: Option<StaticFiles>,
+    group: Option<BackendGroup>,
+    /// 按顺序匹配客户端位置，都不匹配时使用 group
+    geo_groups: Vec<(GeoMatcher, BackendGroup)>,
+    retry_budget: RetryBudget,
+    health_check: Option<JoinHandle<()>>,
+}
+
+impl RouteRunner {
+    /// matcher 为空时为网站的默认路由，route_id 为路由序号或 default，
+    /// 用于区分各路由的共享限流计数和健康检查记录
+    pub async fn new(
+        website_id: ObjectId,
+        route_id: &str,
+        matcher: Option<RouteMatcher>,
+        rewrite: Option<Rewriter>,
+        backends: &[DatabaseWebsiteBackend],
+        geo_backends: &[DatabaseWebsiteGeoBackends],
+        config: DatabaseWebsiteConfig,
+    ) -> anyhow::Result<Self> {
+        // 地区后端组都不可用时需要回退到默认组
+        if backends.is_empty() && !geo_backends.is_empty() {
+            return Err(anyhow::anyhow!("geo_backends requires default backends"));
+        }
+        let (files, group) = match backends.iter().any(|v| v.url.scheme() == "file") {
+            true => {
+                if backends.len() != 1 || !geo_backends.is_empty() {
+                    return Err(anyhow::anyhow!(
+                        "A file backend can't be mixed with other backends"
+                    ));
+                }
+                (Some(StaticFiles::new(&backends[0])?), None)
+            }
+            false => (None, Some(BackendGroup::new(backends, &config).await?)),
+        };
+        let mut geo_groups = Vec::with_capacity(geo_backends.len());
+        for v in geo_backends {
+            geo_groups.push((
+                GeoMatcher::new(&v.countries, &v.regions),
+                BackendGroup::new(&v.backends, &config).await?,
+            ));
+        }
+        let health_check = group.as_ref().and_then(|group| {
+            config.health_check.clone().map(|v| {
+                let backends = group
+                    .backends()
+                    .iter()
+                    .chain(geo_groups.iter().flat_map(|(_, g)| g.backends()))
+                    .cloned()
+                    .collect();
+                spawn_health_check(website_id, route_id.to_string(), v, backends)
+            })
+        });
+        let forward_auth = match &config.forward_auth {
+            Some(v) => Some(ForwardAuth::new(v, &config).await?),
+            None => None,
+        };
+        let cache = match config.cache.enabled && group.is_some() {
+            true => Some(Arc::new(ResponseCache::new(
+                &config.cache,
+                website_id,
+                route_id,
+            )?)),
+            false => {
+                remove_disk_cache(&config.cache, website_id, route_id).await;
+                None
+            }
+        };
+        Ok(Self {
+            matcher,
+            rewrite,
+            files,
+            retry_budget: RetryBudget::new(&config.retry),
+            headers: HeaderRules::new(&config.headers)?,
+            redirects: Redirects::new(&config.redirect)?,
+            compression: Compression::new(&config.compression),
+            rate_limiter: RateLimiter::new(
+                &format!("{website_id}:{route_id}"),
+                &config.rate_limits,
+            )?,
+            basic_auth: BasicAuth::new(&config.basic_auth)?,
+            forward_auth,
+            cache,
+            config,
+            group,
+            geo_groups,
+            health_check,
+        })
+    }
+
+    pub fn is_match(&self, method: &Method, path: &str, headers: &HeaderMap) -> bool {
+        self.matcher
+            .as_ref()
+            .is_none_or(|v| v.is_match(method, path, headers))
+    }
+
+    pub fn rewrite(&self) -> Option<&Rewriter> {
+        self.rewrite.as_ref()
+    }
+
+    pub fn config(&self) -> &DatabaseWebsiteConfig {
+        &self.config
+    }
+
+    pub fn headers(&self) -> &HeaderRules {
+        &self.headers
+    }
+
+    pub fn redirects(&self) -> &Redirects {
+        &self.redirects
+    }
+
+    pub fn compression(&self) -> &Compression {
+        &self.compression
+    }
+
+    pub fn rate_limiter(&self) -> &RateLimiter {
+        &self.rate_limiter
+    }
+
+    pub fn basic_auth(&self) -> Option<&BasicAuth> {
+        self.basic_auth.as_ref()
+    }
+
+    pub fn forward_auth(&self) -> Option<&ForwardAuth> {
+        self.forward_auth.as_ref()
+    }
+
+    pub fn cache(&self) -> Option<&Arc<ResponseCache>> {
+        self.cache.as_ref()
+    }
+
+    pub fn files(&self) -> Option<&StaticFiles> {
+        self.files.as_ref()
+    }
+
+    pub fn group(&self) -> Option<&BackendGroup> {
+        self.group.as_ref()
+    }
+
+    /// 按客户端位置选择后端组，同时返回组的序号（默认组为 0），
+    /// 匹配的组内没有可用后端时回退到默认组
+    pub fn select_group(&self, location: Option<&LookupResult>) -> Option<(usize, &BackendGroup)> {
+        self.geo_groups
+            .iter()
+            .enumerate()
+            .find(|(_, (matcher, group))| {
+                matcher.is_match(location) && group.backends().iter().any(|v| v.is_available())
+            })
+            .map(|(index, (_, group))| (index + 1, group))
+            .or_else(|| self.group.as_ref().map(|group| (0, group)))
+    }
+
+    pub fn retry_budget(&self) -> &RetryBudget {
+        &self.retry_budget
+    }
+}
+
+impl Drop for RouteRunner {
+    fn drop(&mut self) {
+        if let Some(health_check) = self.health_check.take() {
+            health_check.abort();
+        }
+    }
+}
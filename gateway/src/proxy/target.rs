@@ -0,0 +1,154 @@
+use std::fmt::Display;
+
+use hyper::{Method, Uri, http::uri::PathAndQuery};
+
+use crate::proxy::rewrite::Rewriter;
+
+/// 客户端的 request-target 不合法，返回 400
+#[derive(Debug)]
+pub struct InvalidTarget(&'static str);
+
+impl Display for InvalidTarget {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "Invalid request target: {}", self.0)
+    }
+}
+
+impl std::error::Error for InvalidTarget {}
+
+/// 生成发往后端的 request-target（origin-form）
+///
+/// 路径和参数保持客户端的原始编码；absolute-form 只取其中的路径和参数，
+/// `*` 只允许 OPTIONS 使用并原样转发，base 为后端 URL 的路径，作为前缀拼接
+pub fn build_target(
+    method: &Method,
+    uri: &Uri,
+    base: Option<&str>,
+    rewrite: Option<&Rewriter>,
+) -> Result<PathAndQuery, InvalidTarget> {
+    if method == Method::CONNECT {
+        return Err(InvalidTarget("CONNECT is not supported"));
+    }
+    let path = match uri.path() {
+        "" => "/",
+        v => v,
+    };
+    if path == "*" {
+        return match *method == Method::OPTIONS && uri.query().is_none() {
+            true => Ok(PathAndQuery::from_static("*")),
+            false => Err(InvalidTarget("`*` is only allowed for OPTIONS")),
+        };
+    }
+    if !path.starts_with('/') {
+        return Err(InvalidTarget("path must start with `/`"));
+    }
+    let (path, query) = match rewrite {
+        Some(rewrite) => rewrite.rewrite(path, uri.query()),
+        None => (path.to_string(), uri.query().map(|v| v.to_string())),
+    };
+    let mut target = join_path(base.unwrap_or_default(), &path);
+    if let Some(query) = query {
+        target.push('?');
+        target.push_str(&query);
+    }
+    PathAndQuery::try_from(target).map_err(|_| InvalidTarget("malformed path or query"))
+}
+
+/// 把请求路径拼到 base 后面，只处理两者之间的 `/`，不做 `.`、`..` 的解析
+fn join_path(base: &str, path: &str) -> String {
+    let base = base.trim_end_matches('/');
+    let mut joined = String::with_capacity(base.len() + path.len() + 1);
+    if !base.is_empty() && !base.starts_with('/') {
+        joined.push('/');
+    }
+    joined.push_str(base);
+    if !path.starts_with('/') {
+        joined.push('/');
+    }
+    joined.push_str(path);
+    joined
+}
+
+#[cfg(test)]
+mod tests {
+    use shared::models::websites::DatabaseWebsiteRewrite;
+
+    use super::*;
+
+    fn target(method: Method, uri: &str, base: Option<&str>) -> Result<String, InvalidTarget> {
+        build_target(&method, &uri.parse().unwrap(), base, None).map(|v| v.to_string())
+    }
+
+    #[test]
+    fn keeps_query_string() {
+        assert_eq!(target(Method::GET, "/a?b=1&c", None).unwrap(), "/a?b=1&c");
+        assert_eq!(target(Method::GET, "/a?", None).unwrap(), "/a?");
+    }
+
+    #[test]
+    fn keeps_percent_encoding() {
+        assert_eq!(
+            target(Method::GET, "/a%20b/%2F..?q=%E4%B8%AD", None).unwrap(),
+            "/a%20b/%2F..?q=%E4%B8%AD"
+        );
+    }
+
+    #[test]
+    fn uses_path_of_absolute_form() {
+        assert_eq!(
+            target(Method::GET, "http://example.com/a?b=1", None).unwrap(),
+            "/a?b=1"
+        );
+        assert_eq!(
+            target(Method::GET, "http://example.com", None).unwrap(),
+            "/"
+        );
+    }
+
+    #[test]
+    fn asterisk_only_for_options() {
+        assert_eq!(target(Method::OPTIONS, "*", None).unwrap(), "*");
+        assert_eq!(target(Method::OPTIONS, "*", Some("/base")).unwrap(), "*");
+        assert!(target(Method::GET, "*", None).is_err());
+    }
+
+    #[test]
+    fn rejects_connect() {
+        assert!(target(Method::CONNECT, "example.com:443", None).is_err());
+    }
+
+    #[test]
+    fn joins_base_path() {
+        assert_eq!(
+            target(Method::GET, "/a?b", Some("/base")).unwrap(),
+            "/base/a?b"
+        );
+        assert_eq!(
+            target(Method::GET, "/a", Some("/base/")).unwrap(),
+            "/base/a"
+        );
+        assert_eq!(target(Method::GET, "/", Some("/base/")).unwrap(), "/base/");
+        assert_eq!(target(Method::GET, "/a", Some("/")).unwrap(), "/a");
+        assert_eq!(join_path("base", "a"), "/base/a");
+        assert_eq!(join_path("", "/a"), "/a");
+    }
+
+    #[test]
+    fn applies_rewrite() {
+        let rewrite = Rewriter::new(&DatabaseWebsiteRewrite {
+            strip_prefix: Some("/api".to_string()),
+            ..Default::default()
+        })
+        .unwrap();
+        let uri = "/api/users?id=1".parse().unwrap();
+        let target = build_target(&Method::GET, &uri, Some("/v1"), Some(&rewrite)).unwrap();
+        assert_eq!(target, "/v1/users?id=1");
+    }
+
+    #[test]
+    fn invalid_target_is_bad_request() {
+        // wrapper_inner_handle 通过 downcast 把这个错误映射为 400
+        let err = target(Method::GET, "*", None).unwrap_err();
+        assert!(anyhow::Error::from(err).is::<InvalidTarget>());
+    }
+}
@@ -0,0 +1,93 @@
+use std::time::Duration;
+
+use hyper::{
+    HeaderMap,
+    header::{CONNECTION, UPGRADE},
+    upgrade::OnUpgrade,
+};
+use hyper_util::rt::TokioIo;
+use shared::objectid::ObjectId;
+use tokio::{
+    io::{AsyncReadExt, AsyncWriteExt},
+    time::timeout,
+};
+use tracing::{Level, event};
+
+use crate::access::{
+    insert_increase_request_size_log, insert_increase_response_size_log, update_request_size_log,
+    update_response_size_log,
+};
+
+const BUFFER_SIZE: usize = 16 * 1024;
+
+/// 请求是否为 `Connection: Upgrade`（WebSocket 等）
+pub fn is_upgrade_request(headers: &HeaderMap) -> bool {
+    headers.contains_key(UPGRADE)
+        && headers
+            .get_all(CONNECTION)
+            .iter()
+            .filter_map(|v| v.to_str().ok())
+            .flat_map(|v| v.split(','))
+            .any(|v| v.trim().eq_ignore_ascii_case("upgrade"))
+}
+
+/// 101 之后在客户端和后端之间双向转发，超过 idle_timeout 没有数据时断开
+pub async fn tunnel(id: ObjectId, client: OnUpgrade, backend: OnUpgrade, idle_timeout: Duration) {
+    let (client, backend) = match tokio::try_join!(client, backend) {
+        Ok(v) => v,
+        Err(e) => {
+            event!(Level::WARN, "Upgrade of request {id} failed: {e}");
+            return;
+        }
+    };
+    let (mut client_reader, mut client_writer) = tokio::io::split(TokioIo::new(client));
+    let (mut backend_reader, mut backend_writer) = tokio::io::split(TokioIo::new(backend));
+    let mut client_buf = vec![0u8; BUFFER_SIZE];
+    let mut backend_buf = vec![0u8; BUFFER_SIZE];
+    // 上行计入请求大小，下行计入响应大小
+    let (mut sent, mut received) = (0usize, 0usize);
+    let (mut client_eof, mut backend_eof) = (false, false);
+    while !client_eof || !backend_eof {
+        let result = timeout(idle_timeout, async {
+            tokio::select! {
+                n = client_reader.read(&mut client_buf), if !client_eof => {
+                    let n = n?;
+                    if n == 0 {
+                        client_eof = true;
+                        backend_writer.shutdown().await?;
+                    } else {
+                        backend_writer.write_all(&client_buf[..n]).await?;
+                        sent += n;
+                        insert_increase_request_size_log(id, n);
+                    }
+                }
+                n = backend_reader.read(&mut backend_buf), if !backend_eof => {
+                    let n = n?;
+                    if n == 0 {
+                        backend_eof = true;
+                        client_writer.shutdown().await?;
+                    } else {
+                        client_writer.write_all(&backend_buf[..n]).await?;
+                        received += n;
+                        insert_increase_response_size_log(id, n);
+                    }
+                }
+            }
+            Ok::<_, std::io::Error>(())
+        })
+        .await;
+        match result {
+            Ok(Ok(())) => {}
+            Ok(Err(e)) => {
+                event!(Level::DEBUG, "Tunnel of request {id} closed: {e}");
+                break;
+            }
+            Err(_) => {
+                event!(Level::DEBUG, "Tunnel of request {id} idle timeout");
+                break;
+            }
+        }
+    }
+    update_request_size_log(id, sent);
+    update_response_size_log(id, received);
+}
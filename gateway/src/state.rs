@@ -1,43 +1,69 @@
 use std::{
-    net::{IpAddr, SocketAddr},
-    sync::Arc,
+    net::IpAddr,
+    sync::{Arc, OnceLock},
 };
 
-use anyhow::anyhow;
+use geoip::LookupResult;
+use hyper::{HeaderMap, Method};
 use protocols::tls::ProtocolTLS;
+use rustls::ProtocolVersion;
 use shared::{models::websites::DatabaseWebsite, objectid::ObjectId};
-use tokio::net::lookup_host;
 
-use crate::proxy::backends::{BackendConnectionPool, BackendConnectionPoolConfig};
+use crate::proxy::{
+    geo::GeoRules,
+    rewrite::Rewriter,
+    route::{RouteMatcher, RouteRunner},
+};
 
 #[derive(Debug)]
 pub struct WebSiteRunner {
     inner: DatabaseWebsite,
-    pool: Arc<BackendConnectionPool>,
+    routes: Vec<Arc<RouteRunner>>,
+    // 网站自身的 backends，没有配置时只能命中 routes
+    default_route: Option<Arc<RouteRunner>>,
+    geo: GeoRules,
 }
 
 impl WebSiteRunner {
     pub async fn new(inner: DatabaseWebsite) -> anyhow::Result<Self> {
-        // only get first
-        // TODO
-        let backend = inner
-            .backends
-            .first()
-            .ok_or(anyhow!("No found any backends"))?;
-        let hostname = backend.url.host_str().ok_or(anyhow!("No found any host"))?;
-        // dns resolver it
-        let addrs = lookup_host(format!(
-            "{hostname}:{}",
-            backend.url.port_or_known_default().unwrap_or(80)
-        ))
-        .await?
-        .collect::<Vec<SocketAddr>>();
-        let url = backend.url.clone();
+        let mut routes = Vec::with_capacity(inner.routes.len());
+        for (index, route) in inner.routes.iter().enumerate() {
+            routes.push(Arc::new(
+                RouteRunner::new(
+                    inner.id,
+                    &index.to_string(),
+                    Some(RouteMatcher::new(route)?),
+                    route.rewrite.as_ref().map(Rewriter::new).transpose()?,
+                    &route.backends,
+                    &route.geo_backends,
+                    route.config.clone().unwrap_or_else(|| inner.config.clone()),
+                )
+                .await?,
+            ));
+        }
+        let default_route = match inner.backends.is_empty()
+            && inner.geo_backends.is_empty()
+            && !routes.is_empty()
+        {
+            true => None,
+            false => Some(Arc::new(
+                RouteRunner::new(
+                    inner.id,
+                    "default",
+                    None,
+                    None,
+                    &inner.backends,
+                    &inner.geo_backends,
+                    inner.config.clone(),
+                )
+                .await?,
+            )),
+        };
         Ok(Self {
+            geo: GeoRules::new(&inner.config.geo),
             inner,
-            pool: BackendConnectionPool::new(
-                BackendConnectionPoolConfig::new_from_targets(addrs).url(url),
-            ),
+            routes,
+            default_route,
         })
     }
 
@@ -45,22 +71,65 @@ impl WebSiteRunner {
         &self.inner
     }
 
-    pub fn pool(&self) -> &Arc<BackendConnectionPool> {
-        &self.pool
+    pub fn geo(&self) -> &GeoRules {
+        &self.geo
+    }
+
+    /// 按顺序找到第一个匹配的路由，都不匹配时使用默认路由
+    pub fn route(
+        &self,
+        method: &Method,
+        path: &str,
+        headers: &HeaderMap,
+    ) -> Option<Arc<RouteRunner>> {
+        self.routes
+            .iter()
+            .find(|v| v.is_match(method, path, headers))
+            .or(self.default_route.as_ref())
+            .cloned()
     }
 }
 
 #[derive(Debug, Clone)]
 pub struct BaseClientState {
     pub tls: Option<ProtocolTLS>,
+    /// 握手后实际协商的版本
+    pub tls_version: Option<ProtocolVersion>,
     pub remote_addr: IpAddr,
     pub local_addr: IpAddr,
+    /// 第一次使用时按 remote_addr 解析，同一连接只解析一次
+    location: OnceLock<Option<LookupResult>>,
+}
+
+impl BaseClientState {
+    pub fn new(
+        tls: Option<ProtocolTLS>,
+        tls_version: Option<ProtocolVersion>,
+        remote_addr: IpAddr,
+        local_addr: IpAddr,
+    ) -> Self {
+        Self {
+            tls,
+            tls_version,
+            remote_addr,
+            local_addr,
+            location: OnceLock::new(),
+        }
+    }
+
+    /// 客户端位置，数据库不可用时为 None
+    pub fn location(&self) -> Option<&LookupResult> {
+        self.location
+            .get_or_init(|| geoip::lookup(self.remote_addr).ok())
+            .as_ref()
+    }
 }
 
 #[derive(Debug, Clone)]
 pub struct ClientState {
     pub base: Arc<BaseClientState>,
     pub website: Arc<WebSiteRunner>,
+    pub route: Arc<RouteRunner>,
     pub host: String,
     pub id: ObjectId,
 }
@@ -69,12 +138,14 @@ impl ClientState {
     pub fn new(
         base: Arc<BaseClientState>,
         website: Arc<WebSiteRunner>,
+        route: Arc<RouteRunner>,
         host: String,
         id: &ObjectId,
     ) -> Self {
         Self {
             base,
             website,
+            route,
             host,
             id: *id,
         }
@@ -5,14 +5,16 @@ use shared::database::get_database;
 use tracing::{Level, event};
 
 use crate::{
-    proxy::listen,
+    proxy::{geo::load_challenge_secret, listen},
     sync::{
         cert::{AutoCertificate, sync_certificates},
+        ipaccess::sync_ip_access_lists,
         websites::sync_websites,
     },
 };
 
 pub mod cert;
+pub mod ipaccess;
 pub mod websites;
 
 pub static SERVER_CONFIG: LazyLock<Arc<ServerConfig>> = LazyLock::new(|| {
@@ -79,6 +81,21 @@ pub async fn main() -> anyhow::Result<()> {
             Err(e) => event!(Level::ERROR, "Failed to listen certificates: {e}"),
         };
     });
+
+    tokio::spawn(async move {
+        match get_database()
+            .listen_service_fn("ip_access_lists", async |_| {
+                event!(Level::INFO, "Recvied notification, syncing ip access lists");
+                if let Err(e) = sync_ip_access_lists().await {
+                    event!(Level::ERROR, "Failed to sync ip access lists: {e}");
+                }
+            })
+            .await
+        {
+            Ok(()) => {}
+            Err(e) => event!(Level::ERROR, "Failed to listen ip access lists: {e}"),
+        };
+    });
     Ok(())
 }
 
@@ -86,6 +103,12 @@ pub async fn sync_config() -> anyhow::Result<()> {
     event!(Level::DEBUG, "Syncing config at {}", chrono::Local::now());
     event!(Level::DEBUG, "Syncing certificates");
     sync_certificates().await?;
+    event!(Level::DEBUG, "Syncing ip access lists");
+    sync_ip_access_lists().await?;
+    event!(Level::DEBUG, "Loading ip databases");
+    tokio::task::spawn_blocking(geoip::init).await?;
+    event!(Level::DEBUG, "Loading challenge secret");
+    load_challenge_secret().await?;
     event!(Level::DEBUG, "Syncing websites");
     let ports = sync_websites().await?;
     for port in ports {
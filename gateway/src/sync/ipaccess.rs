@@ -0,0 +1,224 @@
+use std::{
+    collections::HashMap,
+    net::IpAddr,
+    sync::{Arc, LazyLock, RwLock},
+};
+
+use shared::{
+    database::{get_database, ipaccess::DatabaseIpAccessListRepository},
+    models::ipaccess::{DatabaseIpAccessList, DatabaseIpAccessMode},
+    objectid::ObjectId,
+};
+use tracing::{Level, event};
+
+static IP_ACCESS_LISTS: LazyLock<RwLock<Arc<IpAccessLists>>> =
+    LazyLock::new(|| RwLock::new(Arc::new(IpAccessLists::default())));
+
+/// IPv4 按 IPv4-mapped IPv6 处理，双栈监听收到的映射地址也能匹配
+fn ip_to_u128(ip: IpAddr) -> u128 {
+    match ip {
+        IpAddr::V4(v) => u128::from(v.to_ipv6_mapped()),
+        IpAddr::V6(v) => u128::from(v),
+    }
+}
+
+fn parse_cidr(value: &str) -> anyhow::Result<(u128, u128)> {
+    let value = value.trim();
+    let (addr, prefix) = match value.split_once('/') {
+        Some((addr, prefix)) => (addr, Some(prefix)),
+        None => (value, None),
+    };
+    let ip = addr.parse::<IpAddr>()?;
+    let (bits, offset) = match ip {
+        IpAddr::V4(_) => (32, 96),
+        IpAddr::V6(_) => (128, 0),
+    };
+    let prefix = prefix
+        .map(|v| v.parse::<u32>())
+        .transpose()?
+        .unwrap_or(bits);
+    if prefix > bits {
+        return Err(anyhow::anyhow!("Invalid prefix length: {value}"));
+    }
+    let mask = match prefix + offset {
+        0 => 0,
+        v => u128::MAX << (128 - v),
+    };
+    let start = ip_to_u128(ip) & mask;
+    Ok((start, start | !mask))
+}
+
+// ---------- 地址区间 ----------
+/// 合并后的有序区间，匹配时二分查找
+#[derive(Debug, Default)]
+struct IpRanges(Vec<(u128, u128)>);
+
+impl IpRanges {
+    fn new(list: &[String]) -> Self {
+        let mut ranges = list
+            .iter()
+            .filter_map(|v| match parse_cidr(v) {
+                Ok(v) => Some(v),
+                Err(e) => {
+                    event!(Level::WARN, "Skip invalid CIDR {v}: {e}");
+                    None
+                }
+            })
+            .collect::<Vec<_>>();
+        ranges.sort_unstable();
+        let mut merged: Vec<(u128, u128)> = Vec::with_capacity(ranges.len());
+        for (start, end) in ranges {
+            match merged.last_mut() {
+                Some(last) if start <= last.1.saturating_add(1) => last.1 = last.1.max(end),
+                _ => merged.push((start, end)),
+            }
+        }
+        Self(merged)
+    }
+
+    fn is_empty(&self) -> bool {
+        self.0.is_empty()
+    }
+
+    fn contains(&self, ip: u128) -> bool {
+        let index = self.0.partition_point(|(start, _)| *start <= ip);
+        index > 0 && self.0[index - 1].1 >= ip
+    }
+}
+
+// ---------- 访问控制列表 ----------
+#[derive(Debug)]
+struct IpAccessList {
+    mode: DatabaseIpAccessMode,
+    allow: IpRanges,
+    deny: IpRanges,
+}
+
+impl IpAccessList {
+    fn new(list: &DatabaseIpAccessList) -> Self {
+        Self {
+            mode: list.mode,
+            allow: IpRanges::new(&list.allow),
+            deny: IpRanges::new(&list.deny),
+        }
+    }
+
+    fn is_allowed(&self, ip: u128) -> bool {
+        let allow = self.allow.contains(ip);
+        let deny = self.deny.contains(ip);
+        match self.mode {
+            DatabaseIpAccessMode::AllowFirst if allow => true,
+            DatabaseIpAccessMode::DenyFirst if deny => false,
+            _ if allow => true,
+            _ if deny => false,
+            // allow 不为空时视为白名单
+            _ => self.allow.is_empty(),
+        }
+    }
+}
+
+#[derive(Debug, Default)]
+struct IpAccessLists {
+    global: Vec<IpAccessList>,
+    websites: HashMap<ObjectId, Vec<IpAccessList>>,
+}
+
+/// 每次收到通知都重新加载全部列表，删除的列表也能及时生效
+pub async fn sync_ip_access_lists() -> anyhow::Result<()> {
+    let mut lists = IpAccessLists::default();
+    for list in get_database().get_ip_access_lists().await? {
+        let parsed = IpAccessList::new(&list);
+        match list.website_id {
+            Some(website_id) => lists.websites.entry(website_id).or_default().push(parsed),
+            None => lists.global.push(parsed),
+        }
+    }
+    event!(
+        Level::INFO,
+        "Loaded {} global and {} website ip access lists",
+        lists.global.len(),
+        lists.websites.values().map(|v| v.len()).sum::<usize>()
+    );
+    *IP_ACCESS_LISTS.write().unwrap() = Arc::new(lists);
+    Ok(())
+}
+
+/// 全局列表和网站的列表都允许时才放行
+pub fn is_ip_allowed(website_id: Option<&ObjectId>, ip: IpAddr) -> bool {
+    let lists = { IP_ACCESS_LISTS.read().unwrap().clone() };
+    let ip = ip_to_u128(ip);
+    lists
+        .global
+        .iter()
+        .chain(
+            website_id
+                .and_then(|v| lists.websites.get(v))
+                .into_iter()
+                .flatten(),
+        )
+        .all(|v| v.is_allowed(ip))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn ip(value: &str) -> u128 {
+        ip_to_u128(value.parse().unwrap())
+    }
+
+    fn ranges(list: &[&str]) -> IpRanges {
+        IpRanges::new(&list.iter().map(|v| v.to_string()).collect::<Vec<_>>())
+    }
+
+    #[test]
+    fn parses_ipv4_cidr() {
+        assert_eq!(
+            parse_cidr("192.168.1.7/24").unwrap(),
+            (ip("192.168.1.0"), ip("192.168.1.255"))
+        );
+        assert_eq!(
+            parse_cidr(" 10.0.0.1 ").unwrap(),
+            (ip("10.0.0.1"), ip("10.0.0.1"))
+        );
+        assert_eq!(
+            parse_cidr("0.0.0.0/0").unwrap(),
+            (ip("0.0.0.0"), ip("255.255.255.255"))
+        );
+    }
+
+    #[test]
+    fn parses_ipv6_cidr() {
+        assert_eq!(
+            parse_cidr("2001:db8::1/32").unwrap(),
+            (
+                ip("2001:db8::"),
+                ip("2001:db8:ffff:ffff:ffff:ffff:ffff:ffff")
+            )
+        );
+        assert_eq!(parse_cidr("::/0").unwrap(), (0, u128::MAX));
+    }
+
+    #[test]
+    fn rejects_invalid_cidr() {
+        assert!(parse_cidr("10.0.0.0/33").is_err());
+        assert!(parse_cidr("::/129").is_err());
+        assert!(parse_cidr("10.0.0.0/x").is_err());
+        assert!(parse_cidr("example.com").is_err());
+    }
+
+    #[test]
+    fn matches_ipv4_mapped_addresses() {
+        let ranges = ranges(&["10.0.0.0/8"]);
+        assert!(ranges.contains(ip("10.1.2.3")));
+        assert!(ranges.contains(ip("::ffff:10.1.2.3")));
+        assert!(!ranges.contains(ip("11.0.0.0")));
+    }
+
+    #[test]
+    fn merges_ranges() {
+        let ranges = ranges(&["10.0.0.0/25", "10.0.0.128/25", "10.0.0.5", "bad"]);
+        assert_eq!(ranges.0, vec![(ip("10.0.0.0"), ip("10.0.0.255"))]);
+        assert!(!ranges.contains(ip("9.255.255.255")));
+    }
+}
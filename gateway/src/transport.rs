@@ -1,28 +1,36 @@
 use std::{
+    future::Future,
     pin::Pin,
     task::{Context, Poll},
+    time::Duration,
 };
 
 use anyhow::Error;
 use bytes::Bytes;
 use http_body::Frame;
-use http_body_util::Full;
+use http_body_util::{Full, combinators::UnsyncBoxBody};
 use hyper::{
     Response,
     body::{Body, Incoming},
 };
 use shared::objectid::ObjectId;
+use tokio::time::{Instant, Sleep};
 
 use crate::access::{
     insert_increase_request_size_log, insert_increase_response_size_log, update_request_size_log,
     update_response_size_log,
 };
 
+// 发往后端的请求 body 类型
+pub type CRequest = UnsyncBoxBody<Bytes, Box<dyn std::error::Error + Send + Sync>>;
+
 // 创建一个统一的 body 类型
 #[derive(Debug)]
 pub enum CResponse {
     Incoming(StatisticsIncoming),
     Error(Full<Bytes>),
+    /// 网关自己生成的响应（静态文件等）
+    Stream(CRequest),
 }
 
 #[derive(Debug)]
@@ -38,6 +46,13 @@ pub struct StatisticsIncoming {
     method: StatisticsIncomingType,
     total_size: usize,
     size: usize,
+    timeout: Option<Box<BodyTimeout>>,
+}
+
+#[derive(Debug)]
+struct BodyTimeout {
+    deadline: Option<Pin<Box<Sleep>>>,
+    idle: Option<(Duration, Pin<Box<Sleep>>)>,
 }
 
 impl StatisticsIncoming {
@@ -48,9 +63,40 @@ impl StatisticsIncoming {
             method,
             size: 0,
             total_size: 0,
+            timeout: None,
         }
     }
 
+    /// 设置读取 body 的截止时间，以及两次数据之间允许的最长间隔
+    pub fn with_timeout(mut self, deadline: Option<Instant>, idle: Option<Duration>) -> Self {
+        if deadline.is_none() && idle.is_none() {
+            return self;
+        }
+        self.timeout = Some(Box::new(BodyTimeout {
+            deadline: deadline.map(|v| Box::pin(tokio::time::sleep_until(v))),
+            idle: idle.map(|v| (v, Box::pin(tokio::time::sleep(v)))),
+        }));
+        self
+    }
+
+    // body 仍在等待数据时检查是否超时
+    fn poll_timeout(&mut self, cx: &mut Context<'_>) -> Poll<()> {
+        let Some(timeout) = &mut self.timeout else {
+            return Poll::Pending;
+        };
+        if let Some(deadline) = &mut timeout.deadline
+            && deadline.as_mut().poll(cx).is_ready()
+        {
+            return Poll::Ready(());
+        }
+        if let Some((_, idle)) = &mut timeout.idle
+            && idle.as_mut().poll(cx).is_ready()
+        {
+            return Poll::Ready(());
+        }
+        Poll::Pending
+    }
+
     pub fn real_size_hint(&self) -> usize {
         self.total_size
     }
@@ -106,6 +152,16 @@ impl http_body::Body for StatisticsIncoming {
             })
         });
 
+        if res.is_pending() {
+            if self.poll_timeout(cx).is_ready() {
+                return Poll::Ready(Some(Err(anyhow::anyhow!("Body timeout").into())));
+            }
+        } else if let Some(timeout) = &mut self.timeout
+            && let Some((duration, idle)) = &mut timeout.idle
+        {
+            idle.as_mut().reset(Instant::now() + *duration);
+        }
+
         // is end stream
         if self.inner.is_end_stream() {
             self.update_size();
@@ -129,6 +185,12 @@ impl From<String> for CResponse {
     }
 }
 
+impl From<Bytes> for CResponse {
+    fn from(b: Bytes) -> Self {
+        CResponse::Error(Full::new(b))
+    }
+}
+
 impl CResponse {
     pub fn new_from_string(value: impl Into<String>) -> Self {
         CResponse::from(value.into())
@@ -151,6 +213,7 @@ impl http_body::Body for CResponse {
             Self::Error(full) => Pin::new(full)
                 .poll_frame(cx)
                 .map(|opt| opt.map(|result| result.map_err(|_| anyhow::anyhow!("error").into()))),
+            Self::Stream(body) => Pin::new(body).poll_frame(cx),
         }
     }
 
@@ -158,6 +221,7 @@ impl http_body::Body for CResponse {
         match self {
             Self::Incoming(incoming) => incoming.is_end_stream(),
             Self::Error(full) => full.is_end_stream(),
+            Self::Stream(body) => body.is_end_stream(),
         }
     }
 
@@ -165,6 +229,7 @@ impl http_body::Body for CResponse {
         match self {
             Self::Incoming(incoming) => incoming.size_hint(),
             Self::Error(full) => full.size_hint(),
+            Self::Stream(body) => body.size_hint(),
         }
     }
 }
@@ -174,6 +239,8 @@ pub enum CResponseResult {
     NotFoundGateway,
     GatewayError(Error),
     BadRequest,
+    /// 客户端地址被访问控制列表拒绝
+    Forbidden,
     Timeout,
 }
 